@@ -0,0 +1,243 @@
+// archive.rs
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+use std::time::{Duration, UNIX_EPOCH};
+use tar::{Archive, Builder, EntryType, HeaderMode};
+
+use crate::output;
+
+pub struct ArchiveEntry {
+    pub path: String,
+    pub mode: u32,
+    pub size: u64,
+    pub mtime: u64,
+    pub is_dir: bool,
+}
+
+/// Packs a directory tree into an in-memory tar archive, keeping permissions and mtimes.
+/// Entries are stored under the directory's own name, so unpacking recreates it.
+pub fn pack_dir(dir: &Path) -> io::Result<Vec<u8>> {
+    let name = dir
+        .canonicalize()?
+        .file_name()
+        .map(|n| n.to_os_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Directory has no name"))?;
+
+    let mut builder = Builder::new(Vec::new());
+    builder.mode(HeaderMode::Complete);
+    builder.follow_symlinks(false);
+    builder.append_dir_all(name, dir)?;
+    builder.into_inner()
+}
+
+/// Total size of the regular files under a directory
+pub fn dir_size(dir: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            total += dir_size(&entry.path())?;
+        } else if file_type.is_file() {
+            total += entry.metadata()?.len();
+        }
+    }
+    Ok(total)
+}
+
+/// Lists an archive's entries without writing anything to disk
+pub fn list(archive: &[u8]) -> io::Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    for entry in Archive::new(archive).entries()? {
+        let entry = entry?;
+        let header = entry.header();
+        entries.push(ArchiveEntry {
+            path: entry.path()?.to_string_lossy().to_string(),
+            mode: header.mode()?,
+            size: header.size()?,
+            mtime: header.mtime()?,
+            is_dir: header.entry_type() == EntryType::Directory,
+        });
+    }
+    Ok(entries)
+}
+
+/// Name of the directory an archive unpacks into
+pub fn root_name(archive: &[u8]) -> Option<String> {
+    let first = list(archive).ok()?.into_iter().next()?;
+    let root = Path::new(&first.path).components().next()?;
+    Some(root.as_os_str().to_string_lossy().to_string())
+}
+
+// The directory every entry is in. Archives with entries elsewhere, like absolute paths
+// or ones going up with "..", are refused as a whole rather than partly unpacked.
+fn checked_root(archive: &[u8]) -> io::Result<String> {
+    let root = root_name(archive).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Archive is empty"))?;
+    for entry in list(archive)? {
+        let mut components = Path::new(&entry.path).components();
+        let inside = components.next().is_some_and(|first| first == Component::Normal(root.as_ref()))
+            && components.all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !inside {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Archive entry {} is outside its directory", entry.path)));
+        }
+    }
+    Ok(root)
+}
+
+/// Restores an archive's directory to `target`, including permissions and modification times.
+/// Everything is extracted into a staging directory first and only moved into place once
+/// complete, replacing what was at `target`. With `private`, group and other permission bits
+/// are dropped. Archives with entries outside their directory (absolute paths, "..") are refused.
+pub fn unpack(archive: &[u8], target: &Path, private: bool) -> io::Result<()> {
+    let root = checked_root(archive)?;
+    if let Some(parent) = target.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+
+    let staging = output::staging_path(target, "tmp");
+    fs::create_dir(&staging)?;
+    let result = (|| {
+        let mut archive = Archive::new(archive);
+        archive.set_preserve_permissions(true);
+        archive.set_preserve_mtime(true);
+        if private {
+            archive.set_mask(0o077);
+        }
+        archive.unpack(&staging)?;
+        replace(&staging.join(&root), target)
+    })();
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+// Moves `from` to `target`, only discarding what was at `target` once the move succeeded
+fn replace(from: &Path, target: &Path) -> io::Result<()> {
+    let Ok(existing) = fs::symlink_metadata(target) else {
+        return fs::rename(from, target);
+    };
+
+    let backup = output::staging_path(target, "old");
+    fs::rename(target, &backup)?;
+    if let Err(e) = fs::rename(from, target) {
+        let _ = fs::rename(&backup, target);
+        return Err(e);
+    }
+    if existing.is_dir() {
+        fs::remove_dir_all(&backup)
+    } else {
+        fs::remove_file(&backup)
+    }
+}
+
+/// Formats an entry like a line of `ls -l`
+pub fn format_entry(entry: &ArchiveEntry) -> String {
+    let mut mode = String::with_capacity(10);
+    mode.push(if entry.is_dir { 'd' } else { '-' });
+    for shift in [6, 3, 0] {
+        let bits = (entry.mode >> shift) & 0o7;
+        mode.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        mode.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        mode.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    let mtime = humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(entry.mtime));
+    format!("{}  {:>10}  {}  {}", mode, entry.size, mtime, entry.path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use tar::Header;
+
+    // An archive with one file at `path`, written into the header as it is: tar's own
+    // setters refuse ".." and absolute paths
+    fn raw_archive(path: &str) -> Vec<u8> {
+        let mut header = Header::new_old();
+        header.as_old_mut().name[..path.len()].copy_from_slice(path.as_bytes());
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut builder = Builder::new(Vec::new());
+        builder.append(&header, &b"evil"[..]).unwrap();
+        builder.into_inner().unwrap()
+    }
+
+    #[test]
+    fn round_trips_a_directory() {
+        let tmp = TempDir::new("archive-round-trip");
+        let src = tmp.path().join("project");
+        fs::create_dir_all(src.join("src").join("empty")).unwrap();
+        fs::write(src.join("README"), "hello").unwrap();
+        fs::write(src.join("src").join("main.rs"), "fn main() {}").unwrap();
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+        fs::File::options().write(true).open(src.join("README")).unwrap().set_modified(mtime).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(src.join("src").join("main.rs"), fs::Permissions::from_mode(0o750)).unwrap();
+        }
+
+        let archive = pack_dir(&src).unwrap();
+        assert_eq!(root_name(&archive).as_deref(), Some("project"));
+        let listed = list(&archive).unwrap().into_iter().map(|e| e.path).collect::<Vec<_>>();
+        assert!(listed.iter().any(|p| p == "project/src/main.rs"), "{:?}", listed);
+
+        let target = tmp.path().join("restored").join("copy");
+        unpack(&archive, &target, false).unwrap();
+        assert_eq!(fs::read_to_string(target.join("README")).unwrap(), "hello");
+        assert_eq!(fs::read_to_string(target.join("src").join("main.rs")).unwrap(), "fn main() {}");
+        assert!(target.join("src").join("empty").is_dir());
+        assert_eq!(fs::metadata(target.join("README")).unwrap().modified().unwrap(), mtime);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&target.join("src").join("main.rs")), 0o750);
+            unpack(&archive, &target, true).unwrap();
+            assert_eq!(mode(&target.join("src").join("main.rs")), 0o700);
+        }
+        // Nothing is left beside the target
+        assert_eq!(fs::read_dir(tmp.path().join("restored")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn refuses_entries_outside_the_directory() {
+        let tmp = TempDir::new("archive-escape");
+        let target = tmp.path().join("out");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("kept"), "before").unwrap();
+
+        for path in ["../evil", "/tmp/evil", "out/../../evil", "./evil"] {
+            let e = unpack(&raw_archive(path), &target, false).unwrap_err();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData, "{}: {}", path, e);
+        }
+        // A second root is outside the first one's directory too
+        let mut builder = Builder::new(Vec::new());
+        builder.append_dir_all("a", tmp.path().join("out")).unwrap();
+        builder.append_dir_all("b", tmp.path().join("out")).unwrap();
+        assert!(unpack(&builder.into_inner().unwrap(), &target, false).is_err());
+
+        assert_eq!(fs::read_to_string(target.join("kept")).unwrap(), "before");
+        assert!(!tmp.path().join("evil").exists());
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn refuses_empty_and_broken_archives_without_touching_the_target() {
+        let tmp = TempDir::new("archive-broken");
+        let target = tmp.path().join("out");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("kept"), "before").unwrap();
+
+        let empty = Builder::new(Vec::new()).into_inner().unwrap();
+        assert_eq!(unpack(&empty, &target, false).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let mut cut = raw_archive("out/file");
+        cut.truncate(512 + 2);
+        assert!(unpack(&cut, &target, false).is_err());
+
+        assert_eq!(fs::read_to_string(target.join("kept")).unwrap(), "before");
+        assert_eq!(fs::read_dir(tmp.path()).unwrap().count(), 1);
+    }
+}
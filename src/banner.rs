@@ -0,0 +1,224 @@
+// banner.rs
+
+//! Art for the top of the main menu. Two banners are built in, and more can be added as
+//! `.txt` files in the banner directory, each named after its file. One with the name of
+//! a built-in banner replaces it. A file may start with settings between `+++` lines:
+//!
+//! ```text
+//! +++
+//! color = "bright-magenta"
+//! greeting = "Hello, friend."
+//! +++
+//! ```
+
+use rand::Rng;
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::style;
+
+/// Shown under a banner that doesn't have its own greeting, and when there is no banner
+pub const GREETING: &str = "Hello, friend.";
+// For banners that don't name a colour
+const DEFAULT_COLOR: &str = "bright-cyan";
+const COLOR_NAMES: [&str; 8] = ["black", "red", "green", "yellow", "blue", "magenta", "cyan", "white"];
+
+const BUILT_IN: [(&str, &str); 2] = [("fsoc", include_str!("ascii/fsoc.txt")), ("crsbrks", include_str!("ascii/crsbrks.txt"))];
+
+/// Where a banner came from
+pub enum Source {
+    BuiltIn,
+    File(PathBuf),
+}
+
+pub struct Banner {
+    pub name: String,
+    pub art: String,
+    /// SGR parameters for the art, e.g. "96" or "38;5;198"
+    pub color: String,
+    pub greeting: String,
+    pub source: Source,
+}
+
+// The settings at the top of a banner file
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Settings {
+    color: Option<String>,
+    greeting: Option<String>,
+}
+
+impl Banner {
+    fn parse(name: &str, text: &str, source: Source) -> Result<Banner, String> {
+        let text = text.replace("\r\n", "\n");
+        let (settings, art) = match text.strip_prefix("+++\n").and_then(|rest| rest.split_once("\n+++\n")) {
+            Some((settings, art)) => (toml::from_str::<Settings>(settings).map_err(|e| e.to_string().trim_end().to_string())?, art),
+            None => (Settings::default(), text.as_str()),
+        };
+        let color = settings.color.as_deref().unwrap_or(DEFAULT_COLOR);
+        let color = sgr(color).ok_or_else(|| format!("unknown color \"{}\" (use a name like cyan or bright-red, 0-255 or #rrggbb)", color))?;
+        let art = art.lines().map(str::trim_end).collect::<Vec<_>>().join("\n");
+        Ok(Banner {
+            name: name.to_string(),
+            art: art.trim_matches('\n').to_string(),
+            color,
+            greeting: settings.greeting.unwrap_or_else(|| GREETING.to_string()),
+            source,
+        })
+    }
+
+    /// Columns of the widest line
+    pub fn width(&self) -> usize {
+        self.art.lines().map(|l| l.chars().count()).max().unwrap_or(0)
+    }
+
+    pub fn height(&self) -> usize {
+        self.art.lines().count()
+    }
+
+    /// The art in its colour, moved right by `indent` spaces
+    pub fn lines(&self, indent: usize) -> Vec<String> {
+        let pad = " ".repeat(indent);
+        self.art.lines().map(|line| format!("{}{}", pad, style::color(&self.color, line))).collect()
+    }
+}
+
+// "red", "bright-red", a 256-colour number or "#rrggbb", as SGR parameters
+fn sgr(color: &str) -> Option<String> {
+    let color = color.trim().to_lowercase();
+    if let Some(i) = COLOR_NAMES.iter().position(|n| *n == color) {
+        return Some((30 + i).to_string());
+    }
+    if let Some(i) = color.strip_prefix("bright-").and_then(|c| COLOR_NAMES.iter().position(|n| *n == c)) {
+        return Some((90 + i).to_string());
+    }
+    if let Ok(index) = color.parse::<u8>() {
+        return Some(format!("38;5;{}", index));
+    }
+    let hex = color.strip_prefix('#').filter(|h| h.len() == 6)?;
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    Some(format!("38;2;{};{};{}", rgb >> 16, (rgb >> 8) & 0xff, rgb & 0xff))
+}
+
+/// The built-in banners, then the `.txt` files in `dir` (if it exists) by name. A file that
+/// can't be read or parsed is left out, and the second list says which and why.
+pub fn load_all(dir: Option<&Path>) -> io::Result<(Vec<Banner>, Vec<String>)> {
+    let mut banners = BUILT_IN
+        .iter()
+        .map(|(name, text)| Banner::parse(name, text, Source::BuiltIn).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+        .collect::<io::Result<Vec<_>>>()?;
+    let mut skipped = Vec::new();
+
+    let entries = match dir.map(fs::read_dir) {
+        Some(Ok(entries)) => entries,
+        Some(Err(e)) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => return Ok((banners, skipped)),
+    };
+    let mut paths = entries
+        .filter_map(Result::ok)
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "txt"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    for path in paths {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let banner = fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Banner::parse(&name, &text, Source::File(path.clone())));
+        match banner {
+            Ok(banner) => {
+                banners.retain(|b| b.name != name);
+                banners.push(banner);
+            }
+            Err(e) => skipped.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    Ok((banners, skipped))
+}
+
+/// The banner `choice` names: "random" picks any of them, "none" none. The files `load_all`
+/// skipped come with it.
+pub fn pick(choice: &str, dir: Option<&Path>) -> io::Result<(Option<Banner>, Vec<String>)> {
+    if choice == "none" {
+        return Ok((None, Vec::new()));
+    }
+    let (mut banners, skipped) = load_all(dir)?;
+    if choice == "random" {
+        // There are always the built-in ones
+        let index = rand::thread_rng().gen_range(0..banners.len());
+        return Ok((Some(banners.swap_remove(index)), skipped));
+    }
+    match banners.iter().position(|b| b.name == choice) {
+        Some(i) => Ok((Some(banners.swap_remove(i)), skipped)),
+        None => {
+            let names = banners.iter().map(|b| b.name.as_str()).collect::<Vec<_>>();
+            Err(io::Error::new(io::ErrorKind::NotFound, format!("No banner named \"{}\" (try {}, random or none)", choice, names.join(", "))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[test]
+    fn colors_become_sgr_parameters() {
+        assert_eq!(sgr("red").as_deref(), Some("31"));
+        assert_eq!(sgr(" Bright-Cyan ").as_deref(), Some("96"));
+        assert_eq!(sgr("198").as_deref(), Some("38;5;198"));
+        assert_eq!(sgr("#ff8000").as_deref(), Some("38;2;255;128;0"));
+        for bad in ["purple", "bright-", "256", "-1", "#fff", "#gggggg", ""] {
+            assert_eq!(sgr(bad), None, "{}", bad);
+        }
+    }
+
+    #[test]
+    fn reads_the_settings_block() {
+        let banner = Banner::parse("x", "+++\r\ncolor = \"green\"\r\ngreeting = \"Hi.\"\r\n+++\r\n\n  art  \n\n", Source::BuiltIn).unwrap();
+        assert_eq!((banner.color.as_str(), banner.greeting.as_str(), banner.art.as_str()), ("32", "Hi.", "  art"));
+
+        let banner = Banner::parse("x", "just art\n+++\n", Source::BuiltIn).unwrap();
+        assert_eq!((banner.color.as_str(), banner.greeting.as_str()), ("96", GREETING));
+        assert_eq!(banner.art, "just art\n+++");
+    }
+
+    #[test]
+    fn rejects_bad_settings() {
+        assert!(Banner::parse("x", "+++\ncolour = \"red\"\n+++\nart", Source::BuiltIn).err().unwrap().contains("colour"));
+        assert!(Banner::parse("x", "+++\ncolor = \"puce\"\n+++\nart", Source::BuiltIn).err().unwrap().contains("unknown color \"puce\""));
+        assert!(Banner::parse("x", "+++\ncolor = [\n+++\nart", Source::BuiltIn).is_err());
+    }
+
+    #[test]
+    fn skips_bad_files_and_keeps_the_rest() {
+        let tmp = TempDir::new("banner-load");
+        tmp.file("fsoc.txt", "mine");
+        tmp.file("bad.txt", "+++\ncolor = \"puce\"\n+++\nart");
+        tmp.file("binary.txt", [0xff, 0xfe]);
+        tmp.file("notes.md", "not a banner");
+
+        let (banners, skipped) = load_all(Some(tmp.path())).unwrap();
+        let names = banners.iter().map(|b| b.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["crsbrks", "fsoc"]);
+        assert_eq!(banners[1].art, "mine");
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped[0].starts_with(&tmp.path().join("bad.txt").display().to_string()));
+        assert!(skipped[1].starts_with(&tmp.path().join("binary.txt").display().to_string()));
+
+        let (banner, skipped) = pick("fsoc", Some(tmp.path())).unwrap();
+        assert_eq!(banner.unwrap().art, "mine");
+        assert_eq!(skipped.len(), 2);
+    }
+
+    #[test]
+    fn a_missing_directory_has_only_the_built_in_banners() {
+        let (banners, skipped) = load_all(Some(Path::new("/nonexistent/banners"))).unwrap();
+        assert_eq!(banners.len(), BUILT_IN.len());
+        assert!(skipped.is_empty());
+        assert_eq!(pick("none", None).unwrap().0.map(|b| b.name), None);
+        assert_eq!(pick("nope", None).err().map(|e| e.kind()), Some(io::ErrorKind::NotFound));
+    }
+}
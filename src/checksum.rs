@@ -0,0 +1,257 @@
+// checksum.rs
+
+use blake2::{Blake2b512, Blake2s256};
+use clap::ValueEnum;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use sha3::{Sha3_256, Sha3_384, Sha3_512};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+    #[value(name = "sha3-256")]
+    Sha3_256,
+    #[value(name = "sha3-384")]
+    Sha3_384,
+    #[value(name = "sha3-512")]
+    Sha3_512,
+    Blake2b,
+    Blake2s,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Sha384 => "SHA-384",
+            HashAlgorithm::Sha512 => "SHA-512",
+            HashAlgorithm::Sha3_256 => "SHA3-256",
+            HashAlgorithm::Sha3_384 => "SHA3-384",
+            HashAlgorithm::Sha3_512 => "SHA3-512",
+            HashAlgorithm::Blake2b => "BLAKE2b-512",
+            HashAlgorithm::Blake2s => "BLAKE2s-256",
+            HashAlgorithm::Blake3 => "BLAKE3",
+        }
+    }
+}
+
+// Streams a reader through any RustCrypto digest in fixed-size chunks
+fn digest_reader<D: Digest>(mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut hasher = D::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(hasher.finalize().to_vec());
+        }
+        hasher.update(&buf[..n]);
+    }
+}
+
+/// Hashes everything read from `reader`
+pub fn hash_reader(algorithm: HashAlgorithm, mut reader: impl Read) -> io::Result<Vec<u8>> {
+    match algorithm {
+        HashAlgorithm::Sha256 => digest_reader::<Sha256>(reader),
+        HashAlgorithm::Sha384 => digest_reader::<Sha384>(reader),
+        HashAlgorithm::Sha512 => digest_reader::<Sha512>(reader),
+        HashAlgorithm::Sha3_256 => digest_reader::<Sha3_256>(reader),
+        HashAlgorithm::Sha3_384 => digest_reader::<Sha3_384>(reader),
+        HashAlgorithm::Sha3_512 => digest_reader::<Sha3_512>(reader),
+        HashAlgorithm::Blake2b => digest_reader::<Blake2b512>(reader),
+        HashAlgorithm::Blake2s => digest_reader::<Blake2s256>(reader),
+        HashAlgorithm::Blake3 => {
+            let mut hasher = blake3::Hasher::new();
+            io::copy(&mut reader, &mut hasher)?;
+            Ok(hasher.finalize().as_bytes().to_vec())
+        }
+    }
+}
+
+/// Hex digest of a file, or of stdin when the path is "-"
+pub fn hash_path(algorithm: HashAlgorithm, path: &Path) -> io::Result<String> {
+    let digest = if path.as_os_str() == "-" {
+        hash_reader(algorithm, io::stdin().lock())?
+    } else {
+        hash_reader(algorithm, File::open(path)?)?
+    };
+    Ok(hex::encode(digest))
+}
+
+/// Expands directories into the regular files beneath them, sorted so manifests are stable.
+/// A path given directly is kept as it is, so `<(command)` can be hashed.
+pub fn collect_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut entries = fs::read_dir(path)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    let mut files = Vec::new();
+    for entry in entries {
+        // Symlinks aren't followed, directory ones can loop back on themselves. FIFOs,
+        // sockets and devices are skipped too: reading a FIFO would block forever.
+        let kind = fs::symlink_metadata(&entry)?.file_type();
+        if kind.is_dir() {
+            files.extend(collect_files(&entry)?);
+        } else if kind.is_file() {
+            files.push(entry);
+        }
+    }
+    Ok(files)
+}
+
+/// Formats a manifest line the way `sha256sum` does, escaping awkward file names
+pub fn manifest_line(digest: &str, path: &Path) -> String {
+    let name = path.to_string_lossy();
+    if name.contains(['\\', '\n', '\r']) {
+        let escaped = name.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r");
+        format!("\\{}  {}", digest, escaped)
+    } else {
+        format!("{}  {}", digest, name)
+    }
+}
+
+pub struct ManifestEntry {
+    pub digest: String,
+    pub path: PathBuf,
+}
+
+/// Parses `sha256sum`-style manifest lines ("<hex>  <path>" or "<hex> *<path>")
+pub fn parse_manifest(contents: &str) -> Result<Vec<ManifestEntry>> {
+    let mut entries = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (escaped, line) = match line.strip_prefix('\\') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let parsed = line.split_once(' ').and_then(|(digest, rest)| {
+            let name = rest.strip_prefix(' ').or_else(|| rest.strip_prefix('*'))?;
+            let valid = !digest.is_empty() && digest.chars().all(|c| c.is_ascii_hexdigit());
+            valid.then_some((digest, name))
+        });
+        let Some((digest, name)) = parsed else {
+            return Err(Error::InvalidInput(format!("Line {} is not a valid checksum line.", number + 1)));
+        };
+
+        let name = if escaped { unescape(name) } else { name.to_string() };
+        entries.push(ManifestEntry { digest: digest.to_lowercase(), path: PathBuf::from(name) });
+    }
+    Ok(entries)
+}
+
+fn unescape(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut chars = name.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    const ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    // `printf abc` into each file, then `sha256sum *` and `sha256sum -b plain.txt` (GNU coreutils 9.1)
+    const SHA256SUM_OUTPUT: &str = "\
+\\ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  a\\nb
+\\ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  back\\\\slash
+ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad  plain.txt
+ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad *plain.txt
+";
+
+    #[test]
+    fn hashes_known_answers() {
+        assert_eq!(hex::encode(hash_reader(HashAlgorithm::Sha256, &b"abc"[..]).unwrap()), ABC);
+        assert_eq!(
+            hex::encode(hash_reader(HashAlgorithm::Blake3, &b""[..]).unwrap()),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+    }
+
+    #[test]
+    fn writes_what_sha256sum_writes() {
+        let lines = ["a\nb", "back\\slash", "plain.txt"].map(|name| manifest_line(ABC, Path::new(name)));
+        let expected = SHA256SUM_OUTPUT.lines().take(3).collect::<Vec<_>>();
+        assert_eq!(lines.to_vec(), expected);
+    }
+
+    #[test]
+    fn reads_what_sha256sum_writes() {
+        let entries = parse_manifest(SHA256SUM_OUTPUT).unwrap();
+        let paths = entries.iter().map(|e| e.path.to_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(paths, ["a\nb", "back\\slash", "plain.txt", "plain.txt"]);
+        assert!(entries.iter().all(|e| e.digest == ABC));
+    }
+
+    #[test]
+    fn escaped_names_round_trip() {
+        for name in ["tab\there", "cr\rlf\n", "ends with \\", "\\n is not a newline", "✅ notes.txt"] {
+            let entries = parse_manifest(&manifest_line("AB12", Path::new(name))).unwrap();
+            assert_eq!(entries[0].path, PathBuf::from(name));
+            assert_eq!(entries[0].digest, "ab12");
+        }
+    }
+
+    #[test]
+    fn collects_only_regular_files() {
+        let tmp = TempDir::new("checksum-collect");
+        let dir = tmp.path().join("tree");
+        fs::create_dir_all(dir.join("inner")).unwrap();
+        fs::write(dir.join("b.txt"), "b").unwrap();
+        fs::write(dir.join("inner").join("a.txt"), "a").unwrap();
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&dir, dir.join("loop")).unwrap();
+            std::os::unix::fs::symlink(dir.join("b.txt"), dir.join("link.txt")).unwrap();
+            let fifo = std::process::Command::new("mkfifo").arg(dir.join("fifo")).status();
+            assert!(fifo.is_ok_and(|s| s.success()), "mkfifo failed");
+        }
+
+        assert_eq!(collect_files(&dir).unwrap(), [dir.join("b.txt"), dir.join("inner").join("a.txt")]);
+        assert_eq!(collect_files(&dir.join("b.txt")).unwrap(), [dir.join("b.txt")]);
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let entries = parse_manifest(&format!("# made by hand\n\n{}  notes.txt\n", ABC)).unwrap();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for line in ["nothing to see", "xyz  notes.txt", format!("{} notes.txt", ABC).as_str(), ABC, "  notes.txt"] {
+            let contents = format!("{}  fine.txt\n{}\n", ABC, line);
+            let Err(Error::InvalidInput(message)) = parse_manifest(&contents) else {
+                panic!("accepted {:?}", line);
+            };
+            assert!(message.starts_with("Line 2 "), "{}", message);
+        }
+    }
+}
@@ -0,0 +1,1112 @@
+// cli.rs
+
+use clap::builder::PossibleValuesParser;
+use clap::{Arg, ArgAction, ArgMatches, CommandFactory, FromArgMatches, Subcommand};
+use clap_complete::Shell;
+use std::fs::{self, File};
+use std::io::{self, IsTerminal, Read};
+use std::path::{Path, PathBuf};
+
+use key_gen_app::Error;
+use key_gen_app::archive;
+use key_gen_app::checksum::{self, HashAlgorithm};
+use key_gen_app::config::{self, Config};
+use key_gen_app::container::{self, FileInfo, Integrity, PayloadKind};
+use key_gen_app::crypto_control::sha256_checksum;
+use key_gen_app::generator::{self, Defaults, Generated, Generator, Kind, Options, Setting};
+use crate::banner::{self, Source};
+use crate::lunchbox;
+use crate::style::{self, paint, paint_err, Tone};
+use key_gen_app::output::{self, Collision};
+use key_gen_app::paper::{Backup, SheetFormat};
+use key_gen_app::mac::{self, Encoding, HmacAlgorithm};
+use key_gen_app::mnemonic;
+use key_gen_app::keyfile;
+use crate::prompt::{ask_collision, read_hidden, read_new_passphrase};
+use key_gen_app::shamir::{self, SecretKind, Share, ShareEncoding};
+use key_gen_app::shred;
+use key_gen_app::signing::{self, PublicKey, SecretKey, DEFAULT_PUBLIC_KEY, DEFAULT_SECRET_KEY, SIGNATURE_EXTENSION};
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Generate keys, UUIDs, passwords and usernames (the -k types, by name)")]
+    Gen {
+        #[command(subcommand)]
+        generator: GenCommand,
+        #[arg(short = 'n', long, global = true, default_value_t = 1, help = "How many to generate, one per line")]
+        count: usize,
+        #[arg(long, global = true, help = "Print JSON, with the entropy of each value")]
+        json: bool,
+    },
+    #[command(about = "Generate an Ed25519 signing key pair (minisign compatible)")]
+    Keypair {
+        #[arg(short = 's', long = "secret-key", help = "Where to write the secret key (default: data/keys/minisign.key)")]
+        secret_key: Option<PathBuf>,
+        #[arg(short = 'p', long = "public-key", help = "Where to write the public key (default: data/keys/minisign.pub)")]
+        public_key: Option<PathBuf>,
+        #[arg(short = 'W', long, help = "Do not encrypt the secret key with a passphrase")]
+        unencrypted: bool,
+        #[arg(short = 'f', long, help = "Overwrite existing key files")]
+        force: bool,
+    },
+    #[command(about = "Create detached signatures (<FILE>.minisig) for files")]
+    Sign {
+        #[arg(required = true, help = "Files to sign, e.g. data/encrypted/notes.txt.locked")]
+        files: Vec<PathBuf>,
+        #[arg(short = 's', long = "secret-key", help = "Secret key file (default: data/keys/minisign.key)")]
+        secret_key: Option<PathBuf>,
+        #[arg(short = 'x', long = "signature", help = "Signature output path (single file only)")]
+        signature: Option<PathBuf>,
+        #[arg(short = 't', long = "trusted-comment", help = "Signed comment (default: timestamp and file name)")]
+        trusted_comment: Option<String>,
+        #[arg(short = 'c', long = "untrusted-comment", default_value = "signature from crypto-bro secret key", help = "Unsigned comment")]
+        untrusted_comment: String,
+    },
+    #[command(about = "Verify a file against its detached signature")]
+    Verify {
+        #[arg(help = "File to verify")]
+        file: PathBuf,
+        #[arg(short = 'p', long = "public-key", help = "Public key file (default: data/keys/minisign.pub)")]
+        public_key: Option<PathBuf>,
+        #[arg(short = 'P', long = "public-key-string", help = "Base64 public key, instead of a file")]
+        public_key_string: Option<String>,
+        #[arg(short = 'x', long = "signature", help = "Signature file (default: <FILE>.minisig)")]
+        signature: Option<PathBuf>,
+    },
+    #[command(about = "Compute or verify an HMAC over a file, string or stdin")]
+    Hmac {
+        #[arg(short = 'a', long, value_enum, default_value = "sha256", help = "Hash function")]
+        algorithm: HmacAlgorithm,
+        #[arg(short = 'f', long, conflicts_with = "string", help = "Read the message from a file (default: stdin)")]
+        file: Option<PathBuf>,
+        #[arg(short = 's', long, help = "Use this string as the message")]
+        string: Option<String>,
+        #[arg(short = 'K', long = "key-file", group = "key_source", help = "Read the key from a file")]
+        key_file: Option<PathBuf>,
+        #[arg(long = "key-env", value_name = "VAR", group = "key_source", help = "Read the key from an environment variable")]
+        key_env: Option<String>,
+        #[arg(long = "key-stdin", group = "key_source", help = "Read the key from the first line of stdin")]
+        key_stdin: bool,
+        #[arg(short = 'e', long, value_enum, default_value = "hex", help = "Output encoding")]
+        encoding: Encoding,
+        #[arg(long, value_name = "TAG", help = "Check against an expected tag (hex or Base64) instead of printing one")]
+        verify: Option<String>,
+    },
+    #[command(about = "Hash files, directories or stdin, or check a checksum manifest")]
+    Hash {
+        #[arg(help = "Files or directories to hash (default: stdin)")]
+        paths: Vec<PathBuf>,
+        #[arg(short = 'a', long, value_enum, default_value = "sha256", help = "Hash function")]
+        algorithm: HashAlgorithm,
+        #[arg(short = 'c', long, value_name = "MANIFEST", conflicts_with_all = ["paths", "output"], help = "Verify the files listed in a sha256sum-style manifest")]
+        check: Option<PathBuf>,
+        #[arg(short = 'o', long, value_name = "MANIFEST", help = "Also write the checksums to a manifest file")]
+        output: Option<PathBuf>,
+        #[arg(short = 'q', long, help = "With --check, only report files that fail")]
+        quiet: bool,
+    },
+    #[command(about = "Encrypt a file, or a whole directory into a single archive")]
+    Encrypt {
+        #[arg(help = "File or directory to encrypt")]
+        path: PathBuf,
+        #[arg(short = 'o', long, help = "Output file (default: data/encrypted/<NAME>.locked)")]
+        output: Option<PathBuf>,
+        #[arg(long = "obfuscate-name", help = "Name the output with a random ID instead of the original name")]
+        obfuscate_name: bool,
+        #[arg(short = 'f', long, conflicts_with = "rename", help = "Overwrite an existing output")]
+        force: bool,
+        #[arg(short = 'r', long, help = "Keep an existing output and write to the next free name-N")]
+        rename: bool,
+        #[arg(long, help = "Overwrite and delete the original once the encrypted output is verified to decrypt")]
+        shred: bool,
+        #[arg(short = 'O', long = "key-out", value_name = "KEY_FILE", help = "Save the generated key to a file (0600) instead of printing it")]
+        key_out: Option<PathBuf>,
+        #[arg(short = 'p', long, requires = "key_out", help = "Protect the saved key file with a passphrase")]
+        passphrase: bool,
+    },
+    #[command(about = "Decrypt a .locked file or restore an encrypted directory")]
+    Decrypt {
+        #[arg(help = "Encrypted file")]
+        file: PathBuf,
+        #[arg(short = 'o', long, help = "Output file, or parent directory for archives (default: data/decrypted)")]
+        output: Option<PathBuf>,
+        #[arg(short = 'l', long, help = "List an archive's contents after authenticating it, without extracting")]
+        list: bool,
+        #[arg(short = 'f', long, conflicts_with = "rename", help = "Overwrite an existing output")]
+        force: bool,
+        #[arg(short = 'r', long, help = "Keep an existing output and write to the next free name-N")]
+        rename: bool,
+        #[arg(long = "keep-permissions", help = "Also restore group and other permission bits (default: owner only)")]
+        keep_permissions: bool,
+        #[arg(short = 'K', long = "key-file", group = "key_source", help = "Read the key from a key file (or a file holding the Base64 key)")]
+        key_file: Option<PathBuf>,
+        #[arg(long = "key-env", value_name = "VAR", group = "key_source", help = "Read the Base64 key from an environment variable")]
+        key_env: Option<String>,
+        #[arg(long = "key-fd", value_name = "FD", group = "key_source", help = "Read the key from an open file descriptor, e.g. 3 with `3< key.txt`")]
+        key_fd: Option<u32>,
+    },
+    #[command(about = "Split a key or secret into Shamir recovery shares")]
+    Split {
+        #[arg(short = 'n', long, help = "Number of shares to create")]
+        shares: u8,
+        #[arg(short = 't', long, help = "Number of shares needed to recover the secret")]
+        threshold: u8,
+        #[arg(short = 'K', long = "key-file", group = "secret_source", help = "Split the key in a key file")]
+        key_file: Option<PathBuf>,
+        #[arg(long = "secret-env", value_name = "VAR", group = "secret_source", help = "Split the secret in an environment variable")]
+        secret_env: Option<String>,
+        #[arg(short = 'e', long, value_enum, default_value = "text", help = "Share encoding")]
+        encoding: ShareEncoding,
+        #[arg(short = 'o', long = "output-dir", value_name = "DIR", help = "Write each share to its own file (0600) instead of printing them")]
+        output_dir: Option<PathBuf>,
+    },
+    #[command(about = "Recover a key or secret from Shamir recovery shares")]
+    Combine {
+        #[arg(help = "Share files (default: read shares from stdin, one per line)")]
+        files: Vec<PathBuf>,
+        #[arg(short = 'O', long = "key-out", value_name = "KEY_FILE", help = "Save a recovered key to a key file (0600) instead of printing it")]
+        key_out: Option<PathBuf>,
+    },
+    #[command(about = "Write a key as BIP39 mnemonic words, or turn words back into the key")]
+    Mnemonic {
+        #[arg(short = 'd', long, help = "Turn words back into the Base64 key")]
+        decode: bool,
+        #[arg(short = 'K', long = "key-file", conflicts_with = "decode", help = "Read the key from a key file")]
+        key_file: Option<PathBuf>,
+    },
+    #[command(about = "Create a printable paper backup of a key (text, or SVG with a QR code)")]
+    Paper {
+        #[arg(short = 'K', long = "key-file", help = "Back up the key in a key file (default: asks for the key)")]
+        key_file: Option<PathBuf>,
+        #[arg(long = "file", value_name = "NAME", help = "Name of the encrypted file the key belongs to")]
+        file_name: Option<String>,
+        #[arg(short = 'o', long, help = "Save the sheet here, as SVG if it ends in .svg (default: print text)")]
+        output: Option<PathBuf>,
+    },
+    #[command(about = "Overwrite and delete decrypted files (default: everything in data/decrypted)")]
+    Shred {
+        #[arg(help = "Files or directories to shred")]
+        paths: Vec<PathBuf>,
+        #[arg(short = 'y', long, help = "Don't ask for confirmation")]
+        yes: bool,
+    },
+    #[command(about = "Print a shell completion script, e.g. `completions bash > ~/.local/share/bash-completion/completions/key-gen-app`")]
+    Completions {
+        #[arg(value_enum, help = "Shell to complete for")]
+        shell: Shell,
+    },
+    #[command(about = "Print the man page, e.g. `man > key-gen-app.1`, or write one per command to a directory")]
+    Man {
+        #[arg(short = 'o', long, value_name = "DIR", help = "Write key-gen-app.1 and a page for each command into DIR")]
+        out_dir: Option<PathBuf>,
+    },
+    #[command(about = "List the banners the main menu can show, or print one")]
+    Banners {
+        #[arg(help = "Banner to print")]
+        name: Option<String>,
+    },
+    #[command(about = "Show the settings in use, or create a config file")]
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    #[command(about = "Print the settings in use, from the config file, CRYPTO_BRO_* variables and defaults")]
+    Show,
+    #[command(about = "Write a config file with every setting at its default, ready to edit")]
+    Init {
+        #[arg(short = 'f', long, help = "Replace an existing config file")]
+        force: bool,
+    },
+}
+
+/// `gen`'s subcommands: one per registry generator, with flags made from its parameters
+pub struct GenCommand {
+    generator: &'static dyn Generator,
+    options: Options,
+}
+
+impl GenCommand {
+    // The registry generator this subcommand runs, with the options given and `defaults` for the rest
+    fn generator(self, defaults: &Defaults) -> (&'static dyn Generator, Options) {
+        (self.generator, defaults.fill(self.options))
+    }
+}
+
+// A generator's subcommand, e.g. `password --length <N> --no-symbols`
+fn gen_subcommand(generator: &'static dyn Generator) -> clap::Command {
+    let mut command = clap::Command::new(generator.name()).about(generator.about());
+    for parameter in generator.parameters() {
+        let arg = Arg::new(parameter.name).long(parameter.name).short(parameter.short);
+        command = command.args(match parameter.kind {
+            Kind::Number { min, max, default } => vec![
+                arg.value_name("N")
+                    .value_parser(clap::value_parser!(u64).range(min as u64..=max as u64))
+                    .help(format!("{}, {} to {}, default {}", parameter.description, min, max, default)),
+            ],
+            Kind::Choice { choices, default } => vec![
+                arg.value_parser(PossibleValuesParser::new(choices))
+                    .help(format!("{}, default {}", parameter.description, default)),
+            ],
+            Kind::Toggle { default, off_flag, off_help } => {
+                let help = if default { format!("{} (the default)", parameter.description) } else { parameter.description.to_string() };
+                vec![
+                    arg.action(ArgAction::SetTrue).overrides_with(off_flag).help(help),
+                    Arg::new(off_flag).long(off_flag).action(ArgAction::SetTrue).overrides_with(parameter.name).help(off_help),
+                ]
+            }
+        });
+    }
+    command
+}
+
+impl FromArgMatches for GenCommand {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        let Some((name, matches)) = matches.subcommand() else {
+            return Err(clap::Error::new(clap::error::ErrorKind::MissingSubcommand));
+        };
+        let generator = generator::find(name).ok_or_else(|| clap::Error::new(clap::error::ErrorKind::InvalidSubcommand))?;
+
+        let mut options = Options::DEFAULT;
+        for parameter in generator.parameters() {
+            let value = match parameter.kind {
+                Kind::Number { .. } => matches.get_one::<u64>(parameter.name).map(|&n| Setting::Number(n as usize)),
+                Kind::Choice { choices, .. } => matches
+                    .get_one::<String>(parameter.name)
+                    .and_then(|choice| choices.iter().find(|c| **c == choice))
+                    .map(|c| Setting::Choice(c)),
+                Kind::Toggle { off_flag, .. } if matches.get_flag(off_flag) => Some(Setting::Toggle(false)),
+                Kind::Toggle { .. } => matches.get_flag(parameter.name).then_some(Setting::Toggle(true)),
+            };
+            if let Some(value) = value {
+                parameter.set(&mut options, value);
+            }
+        }
+        Ok(GenCommand { generator, options })
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = GenCommand::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+impl Subcommand for GenCommand {
+    fn augment_subcommands(command: clap::Command) -> clap::Command {
+        generator::GENERATORS.iter().fold(command, |command, &generator| command.subcommand(gen_subcommand(generator)))
+    }
+
+    fn augment_subcommands_for_update(command: clap::Command) -> clap::Command {
+        GenCommand::augment_subcommands(command)
+    }
+
+    fn has_subcommand(name: &str) -> bool {
+        generator::find(name).is_some()
+    }
+}
+
+// The clap definition of the whole app, named after the binary as it's installed
+fn app_command() -> clap::Command {
+    crate::Args::command().name(env!("CARGO_BIN_NAME"))
+}
+
+/// Writes the config file template to the config file path, for `config init`
+pub fn init_config(explicit: Option<&Path>, force: bool) -> Result<(), Error> {
+    let path = config::file_path(explicit)
+        .ok_or_else(|| Error::InvalidInput("No home directory to put the config file in (use --config).".into()))?;
+    if path.exists() && !force {
+        return Err(Error::Io(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists (use --force to replace it).", path.display()),
+        )));
+    }
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| Error::io(format!("Failed to create {}", dir.display()), e))?;
+    }
+    output::write_atomic(&path, config::TEMPLATE.as_bytes(), false)
+        .map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))?;
+    println!("{} Config file written to: {}", style::bullet(), paint(Tone::Value, path.display()));
+    Ok(())
+}
+
+fn show_config(config: &Config) -> Result<(), Error> {
+    match &config.file {
+        Some(path) => println!("# Settings from {}, with any {}* variables applied", path.display(), config::ENV_PREFIX),
+        None => match config::file_path(None) {
+            Some(path) => println!("# No config file at {}, these are the defaults with any {}* variables applied", path.display(), config::ENV_PREFIX),
+            None => println!("# No config file, these are the defaults with any {}* variables applied", config::ENV_PREFIX),
+        },
+    }
+    println!();
+    print!("{}", config.to_toml()?);
+    Ok(())
+}
+
+fn man(out_dir: Option<&Path>) -> Result<(), Error> {
+    let Some(dir) = out_dir else {
+        return clap_mangen::Man::new(app_command())
+            .render(&mut io::stdout())
+            .map_err(|e| Error::io("Failed to write the man page", e));
+    };
+    fs::create_dir_all(dir).map_err(|e| Error::io(format!("Failed to create {}", dir.display()), e))?;
+    clap_mangen::generate_to(app_command(), dir)
+        .map_err(|e| Error::io(format!("Failed to write man pages to {}", dir.display()), e))?;
+    println!("Man pages written to {}", dir.display());
+    Ok(())
+}
+
+fn banners(name: Option<&str>, dir: Option<&Path>) -> Result<(), Error> {
+    if let Some(name) = name {
+        let (banner, skipped) = banner::pick(name, dir).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => Error::InvalidInput(format!("{}.", e)),
+            _ => Error::io("Failed to load the banners", e),
+        })?;
+        warn_skipped(&skipped);
+        if let Some(banner) = banner {
+            println!("{}", banner.lines(0).join("\n"));
+            println!("\n{}", paint(Tone::Success, &banner.greeting));
+        }
+        return Ok(());
+    }
+
+    let (banners, skipped) = banner::load_all(dir).map_err(|e| Error::io("Failed to load the banners", e))?;
+    warn_skipped(&skipped);
+    let width = banners.iter().map(|b| b.name.chars().count()).max().unwrap_or(0);
+    for banner in &banners {
+        let from = match &banner.source {
+            Source::BuiltIn => "built in".to_string(),
+            Source::File(path) => path.display().to_string(),
+        };
+        let size = format!("{}x{}", banner.width(), banner.height());
+        let name = style::color(&banner.color, &format!("{:<width$}", banner.name));
+        println!("{}  {:>7}  {}", name, size, from);
+    }
+    if let Some(dir) = dir {
+        println!("\nAdd your own as .txt files in {}", dir.display());
+    }
+    Ok(())
+}
+
+fn warn_skipped(skipped: &[String]) {
+    for skipped in skipped {
+        eprintln!("{}", paint_err(Tone::Warning, format_args!("Skipped banner {}", skipped)));
+    }
+}
+
+/// Runs a subcommand given on the command line
+pub fn run(command: Command, config: &Config) -> Result<(), Error> {
+    let keys_dir = config.paths.keys_dir();
+    match command {
+        Command::Gen { generator, count, json } => {
+            let (generator, options) = generator.generator(&config.defaults());
+            print_generated(generator, &options, count, json)
+        }
+        Command::Completions { shell } => {
+            clap_complete::generate(shell, &mut app_command(), env!("CARGO_BIN_NAME"), &mut io::stdout());
+            Ok(())
+        }
+        Command::Man { out_dir } => man(out_dir.as_deref()),
+        Command::Banners { name } => banners(name.as_deref(), config.banner_dir().as_deref()),
+        Command::Config { action: ConfigCommand::Show } => show_config(config),
+        // Handled in main before the config is loaded, so a broken config file can be replaced
+        Command::Config { action: ConfigCommand::Init { .. } } => unreachable!("config init runs before the config is loaded"),
+        Command::Keypair { secret_key, public_key, unencrypted, force } => {
+            let secret_key = secret_key.unwrap_or_else(|| keys_dir.join(DEFAULT_SECRET_KEY));
+            let public_key = public_key.unwrap_or_else(|| keys_dir.join(DEFAULT_PUBLIC_KEY));
+            keypair(&secret_key, &public_key, unencrypted, force)
+        }
+        Command::Sign { files, secret_key, signature, trusted_comment, untrusted_comment } => {
+            let secret_key = secret_key.unwrap_or_else(|| keys_dir.join(DEFAULT_SECRET_KEY));
+            sign(&files, &secret_key, signature, trusted_comment, &untrusted_comment)
+        }
+        Command::Verify { file, public_key, public_key_string, signature } => {
+            let public_key = public_key.unwrap_or_else(|| keys_dir.join(DEFAULT_PUBLIC_KEY));
+            verify(&file, &public_key, public_key_string, signature)
+        }
+        Command::Hmac { algorithm, file, string, key_file, key_env, key_stdin, encoding, verify } => {
+            hmac(algorithm, file, string, key_file, key_env, key_stdin, encoding, verify)
+        }
+        Command::Hash { paths, algorithm, check, output, quiet } => match check {
+            Some(manifest) => check_manifest(algorithm, &manifest, quiet),
+            None => hash(algorithm, paths, output),
+        },
+        Command::Encrypt { path, output, obfuscate_name, force, rename, shred, key_out, passphrase } => {
+            let policy = collision_policy(force, rename);
+            encrypt(config, &path, output, obfuscate_name, policy, shred, key_out, passphrase)
+        }
+        Command::Decrypt { file, output, list, force, rename, keep_permissions, key_file, key_env, key_fd } => {
+            let key = read_decryption_key(key_file, key_env, key_fd)?;
+            decrypt(&file, output, &config.paths.decrypted_dir(), list, collision_policy(force, rename), !keep_permissions, &key)
+        }
+        Command::Shred { paths, yes } => shred(paths, &config.paths.decrypted_dir(), yes),
+        Command::Split { shares, threshold, key_file, secret_env, encoding, output_dir } => {
+            split(shares, threshold, key_file, secret_env, encoding, output_dir)
+        }
+        Command::Combine { files, key_out } => combine(files, key_out),
+        Command::Mnemonic { decode, key_file } => words(decode, key_file),
+        Command::Paper { key_file, file_name, output } => paper(key_file, file_name, output),
+    }
+}
+
+/// Prints `count` values from a generator, one per line or as a JSON array
+pub fn print_generated(generator: &'static dyn Generator, options: &Options, count: usize, json: bool) -> Result<(), Error> {
+    let generated = (0..count).map(|_| generator::generate(generator, options)).collect::<Result<Vec<_>, _>>()?;
+    if json {
+        let objects = generated.iter().map(Generated::to_json).collect::<Vec<_>>();
+        println!("[{}]", objects.join(","));
+    } else {
+        for g in &generated {
+            println!("{}", g.value);
+        }
+    }
+    Ok(())
+}
+
+fn signature_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".");
+    name.push(SIGNATURE_EXTENSION);
+    PathBuf::from(name)
+}
+
+fn keypair(secret_path: &Path, public_path: &Path, unencrypted: bool, force: bool) -> Result<(), Error> {
+    if !force {
+        for path in [secret_path, public_path] {
+            if path.exists() {
+                return Err(Error::Io(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} already exists (use --force to overwrite).", path.display()),
+                )));
+            }
+        }
+    }
+
+    let passphrase = if unencrypted {
+        None
+    } else {
+        let pass = read_new_passphrase("Passphrase for the new secret key: ")?;
+        if pass.is_empty() {
+            return Err(Error::InvalidInput("Passphrase cannot be empty (use -W for an unencrypted key).".into()));
+        }
+        println!("Deriving encryption key...");
+        Some(pass)
+    };
+
+    let (pk, sk) = signing::generate_keypair()?;
+    signing::write_key_file(secret_path, &sk.to_file_string(passphrase.as_deref())?, true, force)?;
+    signing::write_key_file(public_path, &pk.to_file_string(), false, force)?;
+
+    println!("\n{}", paint(Tone::Success, style::text("✅ Key pair generated")));
+    println!("{} Key ID: {}", style::bullet(), paint(Tone::Id, signing::key_id_hex(&pk.key_id)));
+    println!("{} Secret key: {}", style::bullet(), paint(Tone::Value, secret_path.display()));
+    println!("{} Public key: {}", style::bullet(), paint(Tone::Value, public_path.display()));
+    println!("\n{} Keep the secret key private. Share the public key with anyone who verifies your files.\n", paint(Tone::Warning, "IMPORTANT:"));
+    Ok(())
+}
+
+fn sign(
+    files: &[PathBuf],
+    secret_path: &Path,
+    signature: Option<PathBuf>,
+    trusted_comment: Option<String>,
+    untrusted_comment: &str,
+) -> Result<(), Error> {
+    if signature.is_some() && files.len() > 1 {
+        return Err(Error::InvalidInput("--signature can only be used when signing a single file.".into()));
+    }
+
+    let contents = fs::read_to_string(secret_path)
+        .map_err(|e| Error::io(format!("Failed to read secret key {}", secret_path.display()), e))?;
+    let key = SecretKey::decode(&contents, || read_hidden("Passphrase: "))?;
+
+    for file in files {
+        let comment = trusted_comment.clone().unwrap_or_else(|| signing::default_trusted_comment(file));
+        let sig = signing::sign_file(&key, file, &comment, untrusted_comment)?;
+        let out_path = signature.clone().unwrap_or_else(|| signature_path(file));
+        output::write_atomic(&out_path, sig.as_bytes(), false)
+            .map_err(|e| Error::io(format!("Failed to write {}", out_path.display()), e))?;
+        println!("{} {} → {}", paint(Tone::Success, style::text("✅ Signed")), file.display(), paint(Tone::Value, out_path.display()));
+    }
+    Ok(())
+}
+
+fn verify(
+    file: &Path,
+    public_path: &Path,
+    public_key_string: Option<String>,
+    signature: Option<PathBuf>,
+) -> Result<(), Error> {
+    let key = match public_key_string {
+        Some(s) => PublicKey::decode(&s)?,
+        None => {
+            let contents = fs::read_to_string(public_path)
+                .map_err(|e| Error::io(format!("Failed to read public key {}", public_path.display()), e))?;
+            PublicKey::decode(&contents)?
+        }
+    };
+
+    let sig_path = signature.unwrap_or_else(|| signature_path(file));
+    let sig = fs::read_to_string(&sig_path)
+        .map_err(|e| Error::io(format!("Failed to read signature {}", sig_path.display()), e))?;
+
+    let trusted_comment = signing::verify_file(&key, file, &sig)?;
+    println!("{}", paint(Tone::Success, style::text("✅ Signature and comment signature verified")));
+    println!("{} Key ID: {}", style::bullet(), paint(Tone::Id, signing::key_id_hex(&key.key_id)));
+    println!("{} Trusted comment: {}", style::bullet(), trusted_comment);
+    Ok(())
+}
+
+// Reads an HMAC key from whichever source was given, prompting as a last resort
+fn read_hmac_key(
+    key_file: Option<PathBuf>,
+    key_env: Option<String>,
+    key_stdin: bool,
+    message_on_stdin: bool,
+) -> Result<Vec<u8>, Error> {
+    let key = if let Some(path) = key_file {
+        let mut bytes = fs::read(&path).map_err(|e| Error::io(format!("Failed to read key file {}", path.display()), e))?;
+        // Editors add a trailing newline that is never part of the secret
+        if bytes.ends_with(b"\n") {
+            bytes.pop();
+            if bytes.ends_with(b"\r") {
+                bytes.pop();
+            }
+        }
+        bytes
+    } else if let Some(var) = key_env {
+        std::env::var(&var).map_err(|_| Error::InvalidInput(format!("Environment variable {} is not set.", var)))?.into_bytes()
+    } else if key_stdin {
+        if message_on_stdin {
+            return Err(Error::InvalidInput("--key-stdin needs the message from --file or --string.".into()));
+        }
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).map_err(|e| Error::io("Failed to read key", e))?;
+        line.trim_end_matches(['\r', '\n']).as_bytes().to_vec()
+    } else if message_on_stdin && !io::stdin().is_terminal() {
+        return Err(Error::InvalidInput("Provide the key with --key-file, --key-env or --key-stdin when piping the message.".into()));
+    } else {
+        read_hidden("HMAC key: ")?.into_bytes()
+    };
+
+    if key.is_empty() {
+        return Err(Error::InvalidInput("HMAC key is empty.".into()));
+    }
+    Ok(key)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn hmac(
+    algorithm: HmacAlgorithm,
+    file: Option<PathBuf>,
+    string: Option<String>,
+    key_file: Option<PathBuf>,
+    key_env: Option<String>,
+    key_stdin: bool,
+    encoding: Encoding,
+    verify: Option<String>,
+) -> Result<(), Error> {
+    let message_on_stdin = file.is_none() && string.is_none();
+    let key = read_hmac_key(key_file, key_env, key_stdin, message_on_stdin)?;
+    let expected = verify.map(|tag| mac::decode_tag(algorithm, &tag)).transpose()?;
+
+    let reader: Box<dyn Read> = match (&file, string) {
+        (Some(path), _) => Box::new(
+            File::open(path).map_err(|e| Error::io(format!("Failed to open {}", path.display()), e))?,
+        ),
+        (None, Some(s)) => Box::new(io::Cursor::new(s.into_bytes())),
+        (None, None) => Box::new(io::stdin()),
+    };
+
+    match expected {
+        Some(tag) => {
+            let valid = mac::verify_hmac(algorithm, &key, reader, &tag)
+                .map_err(|e| Error::io("Failed to read message", e))?;
+            if !valid {
+                return Err(Error::Corrupted(format!(
+                    "{} mismatch: the message or key does not match the expected tag.",
+                    algorithm.name()
+                )));
+            }
+            println!("{}", paint(Tone::Success, format_args!("{} {} verified", style::text("✅"), algorithm.name())));
+        }
+        None => {
+            let tag = mac::compute_hmac(algorithm, &key, reader)
+                .map_err(|e| Error::io("Failed to read message", e))?;
+            println!("{}", encoding.encode(&tag));
+        }
+    }
+    Ok(())
+}
+
+// What tells a file apart from every other, whatever path leads to it: its device and inode
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(path: &Path) -> Option<PathBuf> {
+    path.canonicalize().ok()
+}
+
+fn hash(algorithm: HashAlgorithm, paths: Vec<PathBuf>, output: Option<PathBuf>) -> Result<(), Error> {
+    let paths = if paths.is_empty() { vec![PathBuf::from("-")] } else { paths };
+
+    // A manifest written into the hashed directory must not list itself, however its path is written
+    let manifest_id = output.as_deref().and_then(file_id);
+    let mut manifest = String::new();
+    for path in &paths {
+        let files = checksum::collect_files(path)
+            .map_err(|e| Error::io(format!("Failed to read {}", path.display()), e))?;
+        for file in files {
+            if manifest_id.is_some() && file_id(&file) == manifest_id {
+                continue;
+            }
+            let digest = checksum::hash_path(algorithm, &file)
+                .map_err(|e| Error::io(format!("Failed to read {}", file.display()), e))?;
+            let line = checksum::manifest_line(&digest, &file);
+            println!("{}", line);
+            manifest.push_str(&line);
+            manifest.push('\n');
+        }
+    }
+
+    if let Some(out_path) = output {
+        output::write_atomic(&out_path, manifest.as_bytes(), false)
+            .map_err(|e| Error::io(format!("Failed to write {}", out_path.display()), e))?;
+        eprintln!("{} {}", paint_err(Tone::Success, format_args!("{} {} manifest saved to:", style::text("✅"), algorithm.name())), paint_err(Tone::Value, out_path.display()));
+    }
+    Ok(())
+}
+
+fn check_manifest(algorithm: HashAlgorithm, manifest: &Path, quiet: bool) -> Result<(), Error> {
+    let contents = fs::read_to_string(manifest)
+        .map_err(|e| Error::io(format!("Failed to read manifest {}", manifest.display()), e))?;
+    let entries = checksum::parse_manifest(&contents)?;
+    if entries.is_empty() {
+        return Err(Error::InvalidInput(format!("{} contains no checksum lines.", manifest.display())));
+    }
+
+    let (mut mismatched, mut unreadable) = (0, 0);
+    for entry in &entries {
+        match checksum::hash_path(algorithm, &entry.path) {
+            Ok(digest) if digest == entry.digest => {
+                if !quiet {
+                    println!("{}: OK", entry.path.display());
+                }
+            }
+            Ok(digest) => {
+                mismatched += 1;
+                if digest.len() != entry.digest.len() {
+                    println!("{}: FAILED (digest length doesn't match {})", entry.path.display(), algorithm.name());
+                } else {
+                    println!("{}: FAILED", entry.path.display());
+                }
+            }
+            Err(e) => {
+                unreadable += 1;
+                println!("{}: FAILED open or read ({})", entry.path.display(), e);
+            }
+        }
+    }
+
+    match (mismatched, unreadable) {
+        (0, 0) => Ok(()),
+        _ => Err(Error::Corrupted(format!(
+            "WARNING: {} computed checksum(s) did NOT match, {} listed file(s) could not be read.",
+            mismatched, unreadable
+        ))),
+    }
+}
+
+// Interactive runs ask about existing outputs, scripted runs fail unless told otherwise
+fn collision_policy(force: bool, rename: bool) -> Collision {
+    if force {
+        Collision::Overwrite
+    } else if rename {
+        Collision::Rename
+    } else if io::stdin().is_terminal() {
+        Collision::Ask
+    } else {
+        Collision::Fail
+    }
+}
+
+// Resolves an output path against the collision policy, treating a cancel as an error
+fn claim_output(path: &Path, policy: Collision) -> Result<PathBuf, Error> {
+    output::resolve(path, policy, ask_collision)?.ok_or_else(|| Error::Cancelled("Cancelled, nothing was written.".into()))
+}
+
+fn check_size(size: u64) -> Result<(), Error> {
+    let max_size = lunchbox::max_allowed_file_size();
+    if size > max_size {
+        return Err(Error::InvalidInput(format!("Input too large. Limit is {:.2} MiB", max_size as f64 / 1024.0 / 1024.0)));
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn encrypt(
+    config: &Config,
+    path: &Path,
+    output: Option<PathBuf>,
+    obfuscate_name: bool,
+    policy: Collision,
+    shred: bool,
+    key_out: Option<PathBuf>,
+    passphrase: bool,
+) -> Result<(), Error> {
+    let read_err = |e| Error::io(format!("Failed to read {}", path.display()), e);
+    let (kind, plaintext) = if path.is_dir() {
+        check_size(archive::dir_size(path).map_err(read_err)?)?;
+        (PayloadKind::Archive, archive::pack_dir(path).map_err(read_err)?)
+    } else {
+        check_size(fs::metadata(path).map_err(read_err)?.len())?;
+        (PayloadKind::File, fs::read(path).map_err(read_err)?)
+    };
+
+    let info = FileInfo::read(path).map_err(read_err)?;
+    let key_bytes = lunchbox::generate_key();
+    let key = base64::encode(key_bytes);
+    let encrypted = container::seal(&plaintext, kind, &info, &key_bytes)?;
+
+    let out_path = match output {
+        Some(p) => p,
+        None => {
+            let name = info.name.as_deref().ok_or_else(|| Error::InvalidInput("Input has no file name.".into()))?;
+            config.paths.encrypted_dir().join(lunchbox::locked_file_name(name, obfuscate_name))
+        }
+    };
+    let out_path = claim_output(&out_path, policy)?;
+
+    // The key is saved before the output exists, so a failure can never strand an encrypted file
+    let key_path = match key_out {
+        Some(key_path) => {
+            let key_path = claim_output(&key_path, policy)?;
+            let pass = if passphrase {
+                let pass = read_new_passphrase("Passphrase for the key file: ")?;
+                if pass.is_empty() {
+                    return Err(Error::InvalidInput("Passphrase cannot be empty.".into()));
+                }
+                Some(pass)
+            } else {
+                None
+            };
+            keyfile::save(&key_path, &key_bytes, pass.as_deref(), config.encryption.kdf_rounds)?;
+            Some(key_path)
+        }
+        None => None,
+    };
+
+    output::write_atomic(&out_path, &encrypted, false)
+        .map_err(|e| Error::io(format!("Failed to write {}", out_path.display()), e))?;
+
+    println!("{}", paint(Tone::Success, style::text("✅ Encryption Complete")));
+    println!("{} Encrypted file saved to: {}", style::bullet(), paint(Tone::Value, out_path.display()));
+    println!("{} Checksum (SHA-256): {}", style::bullet(), paint(Tone::Id, sha256_checksum(&plaintext)));
+    println!("{} Key ID: {}", style::bullet(), paint(Tone::Id, container::key_id_hex(&container::key_id(&key_bytes))));
+    match &key_path {
+        Some(key_path) => {
+            println!("{} Key file saved to: {}", style::bullet(), paint(Tone::Value, key_path.display()));
+            println!("\n{} Keep the key file safe and separate from the encrypted file.", paint(Tone::Warning, "IMPORTANT:"));
+        }
+        None => {
+            println!("{} Decryption key: {}", style::bullet(), paint(Tone::Value, key));
+            println!("{} As words: {}", style::bullet(), paint(Tone::Value, mnemonic::encode_key(&key_bytes)?));
+            println!("\n{} Keep your decryption key safe. It is not stored anywhere else.", paint(Tone::Warning, "IMPORTANT:"));
+        }
+    }
+
+    if shred {
+        let count = shred::shred_source(path, &out_path, &plaintext, &key_bytes)?;
+        println!("{} Verified the encrypted output and shredded the original ({} file(s)).", style::bullet(), count);
+        println!("{} {}", paint(Tone::Warning, "NOTE:"), shred::CAVEAT);
+    }
+    Ok(())
+}
+
+// Loads a decryption key from a key file, environment variable or file descriptor, else asks for it.
+// Wrapped key files ask for their passphrase.
+fn read_decryption_key(key_file: Option<PathBuf>, key_env: Option<String>, key_fd: Option<u32>) -> Result<Vec<u8>, Error> {
+    let contents = if let Some(path) = key_file {
+        fs::read_to_string(&path).map_err(|e| Error::io(format!("Failed to read key file {}", path.display()), e))?
+    } else if let Some(var) = key_env {
+        std::env::var(&var).map_err(|_| Error::InvalidInput(format!("Environment variable {} is not set.", var)))?
+    } else if let Some(fd) = key_fd {
+        keyfile::read_fd(fd).map_err(|e| Error::io(format!("Failed to read key from file descriptor {}", fd), e))?
+    } else {
+        read_hidden("Decryption key (Base64 or words): ")?
+    };
+    keyfile::decode(&contents, || read_hidden("Key file passphrase: "))
+}
+
+fn decrypt(
+    file: &Path,
+    output: Option<PathBuf>,
+    decrypted_dir: &Path,
+    list: bool,
+    policy: Collision,
+    private: bool,
+    key_bytes: &[u8],
+) -> Result<(), Error> {
+    check_size(fs::metadata(file).map_err(|e| Error::io(format!("Failed to read {}", file.display()), e))?.len())?;
+    let ciphertext = fs::read(file).map_err(|e| Error::io(format!("Failed to read {}", file.display()), e))?;
+
+    let decrypted = container::open(&ciphertext, key_bytes).map_err(|e| e.context("Decryption failed"))?;
+
+    if list {
+        if decrypted.kind != PayloadKind::Archive {
+            return Err(Error::InvalidInput(format!("{} is a single encrypted file, not a directory archive.", file.display())));
+        }
+        let entries = archive::list(&decrypted.plaintext).map_err(|e| Error::io("Failed to read archive", e))?;
+        for entry in &entries {
+            println!("{}", archive::format_entry(entry));
+        }
+        return Ok(());
+    }
+
+    match decrypted.kind {
+        PayloadKind::File => {
+            let out_path = match output {
+                Some(p) if p.is_dir() => p.join(lunchbox::restored_file_name(&decrypted.info, file)),
+                Some(p) => p,
+                None => decrypted_dir.join(lunchbox::restored_file_name(&decrypted.info, file)),
+            };
+            let out_path = claim_output(&out_path, policy)?;
+            output::write_atomic(&out_path, &decrypted.plaintext, true)
+                .map_err(|e| Error::io(format!("Failed to write {}", out_path.display()), e))?;
+            println!("{}", paint(Tone::Success, style::text("✅ Decryption Complete")));
+            println!("{} Decrypted file saved to: {}", style::bullet(), paint(Tone::Value, out_path.display()));
+            if let Err(e) = decrypted.info.apply(&out_path, private) {
+                println!("{}", paint(Tone::Warning, format_args!("Could not restore file permissions or timestamps: {}", e)));
+            }
+        }
+        PayloadKind::Archive => {
+            let dest = output.unwrap_or_else(|| decrypted_dir.to_path_buf());
+            let target = claim_output(&dest.join(lunchbox::restored_file_name(&decrypted.info, file)), policy)?;
+            archive::unpack(&decrypted.plaintext, &target, private)
+                .map_err(|e| Error::io("Failed to extract archive", e))?;
+            println!("{}", paint(Tone::Success, style::text("✅ Decryption Complete")));
+            println!("{} Directory restored to: {}", style::bullet(), paint(Tone::Value, target.display()));
+        }
+    }
+
+    match decrypted.integrity {
+        Integrity::Verified => println!("{} Integrity check: {}", style::bullet(), paint(Tone::Success, "PASSED")),
+        Integrity::Unverified => println!("{} Integrity check: {} (old format without a checksum)", style::bullet(), paint(Tone::Warning, "UNAVAILABLE")),
+    }
+    Ok(())
+}
+
+fn shred(paths: Vec<PathBuf>, decrypted_dir: &Path, yes: bool) -> Result<(), Error> {
+    let paths = if paths.is_empty() {
+        shred::entries(decrypted_dir).map_err(|e| Error::io(format!("Failed to read {}", decrypted_dir.display()), e))?
+    } else {
+        paths
+    };
+    if paths.is_empty() {
+        println!("Nothing to shred.");
+        return Ok(());
+    }
+
+    if !yes {
+        if !io::stdin().is_terminal() {
+            return Err(Error::InvalidInput("Refusing to shred without confirmation (use --yes).".into()));
+        }
+        println!("These will be overwritten and deleted:");
+        for path in &paths {
+            println!("  {}", path.display());
+        }
+        eprint!("Continue? (y/N): ");
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !input.trim().eq_ignore_ascii_case("y") {
+            return Err(Error::Cancelled("Cancelled, nothing was shredded.".into()));
+        }
+    }
+
+    let mut count = 0;
+    for path in &paths {
+        count += shred::shred_path(path).map_err(|e| Error::io(format!("Failed to shred {}", path.display()), e))?;
+    }
+    println!("{}", paint(Tone::Success, format_args!("{} Shredded {} file(s).", style::text("✅"), count)));
+    println!("{} {}", paint(Tone::Warning, "NOTE:"), shred::CAVEAT);
+    Ok(())
+}
+
+fn split(
+    shares: u8,
+    threshold: u8,
+    key_file: Option<PathBuf>,
+    secret_env: Option<String>,
+    encoding: ShareEncoding,
+    output_dir: Option<PathBuf>,
+) -> Result<(), Error> {
+    let (kind, secret) = if let Some(path) = key_file {
+        let contents = fs::read_to_string(&path).map_err(|e| Error::io(format!("Failed to read key file {}", path.display()), e))?;
+        (SecretKind::Key, keyfile::decode(&contents, || read_hidden("Key file passphrase: "))?)
+    } else if let Some(var) = secret_env {
+        shamir::classify(&std::env::var(&var).map_err(|_| Error::InvalidInput(format!("Environment variable {} is not set.", var)))?)
+    } else {
+        shamir::classify(&read_hidden("Key or secret to split: ")?)
+    };
+
+    let split = shamir::split(&secret, kind, threshold, shares)?;
+    match kind {
+        SecretKind::Key => eprintln!(
+            "Splitting a 256-bit key (key ID {}) into {} shares, any {} recover it.",
+            container::key_id_hex(&container::key_id(&secret)),
+            shares,
+            threshold
+        ),
+        SecretKind::Text => eprintln!("Splitting a {}-byte secret into {} shares, any {} recover it.", secret.len(), shares, threshold),
+    }
+
+    match output_dir {
+        Some(dir) => {
+            for share in &split {
+                let path = dir.join(format!("share-{}-of-{}.txt", share.index, shares));
+                let path = claim_output(&path, collision_policy(false, false))?;
+                output::write_atomic(&path, shamir::share_file_string(share, shares, encoding).as_bytes(), true)
+                    .map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))?;
+                println!("{} Share {} saved to: {}", style::bullet(), share.index, paint(Tone::Value, path.display()));
+            }
+        }
+        None => {
+            for share in &split {
+                println!("{}", share.encode(encoding));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn combine(files: Vec<PathBuf>, key_out: Option<PathBuf>) -> Result<(), Error> {
+    let mut shares = Vec::new();
+    for path in &files {
+        let contents = fs::read_to_string(path).map_err(|e| Error::io(format!("Failed to read {}", path.display()), e))?;
+        shares.push(shamir::share_from_file_contents(&contents).map_err(|e| e.context(path.display()))?);
+    }
+
+    if files.is_empty() {
+        if io::stdin().is_terminal() {
+            // Ask for one share at a time until the first share says how many are needed
+            loop {
+                let needed = shares.first().map(|s: &Share| s.threshold as usize);
+                if needed.is_some_and(|n| shares.len() >= n) {
+                    break;
+                }
+                let prompt = match needed {
+                    Some(n) => format!("Share {} of {}: ", shares.len() + 1, n),
+                    None => "Share 1: ".to_string(),
+                };
+                let line = read_hidden(&prompt)?;
+                match Share::decode(&line) {
+                    Ok(share) => shares.push(share),
+                    Err(e) => eprintln!("{}", paint_err(Tone::Error, &e)),
+                }
+            }
+        } else {
+            for (number, line) in io::stdin().lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                shares.push(Share::decode(&line).map_err(|e| e.context(format!("Line {}", number + 1)))?);
+            }
+        }
+    }
+
+    let (kind, secret) = shamir::combine(&shares)?;
+    match kind {
+        SecretKind::Key => {
+            let id = container::key_id_hex(&container::key_id(&secret));
+            match key_out {
+                Some(path) => {
+                    let path = claim_output(&path, collision_policy(false, false))?;
+                    keyfile::save(&path, &secret, None, keyfile::DEFAULT_PBKDF2_ROUNDS)?;
+                    println!("{} (key ID {})", paint(Tone::Success, style::text("✅ Key recovered")), id);
+                    println!("{} Key file saved to: {}", style::bullet(), paint(Tone::Value, path.display()));
+                }
+                None => {
+                    eprintln!("{} (key ID {})", paint_err(Tone::Success, style::text("✅ Key recovered")), id);
+                    println!("{}", base64::encode(&secret));
+                }
+            }
+        }
+        SecretKind::Text => {
+            if key_out.is_some() {
+                return Err(Error::InvalidInput("The recovered secret is text, not a key.".into()));
+            }
+            eprintln!("{}", paint_err(Tone::Success, style::text("✅ Secret recovered")));
+            println!("{}", String::from_utf8_lossy(&secret));
+        }
+    }
+    Ok(())
+}
+
+fn words(decode: bool, key_file: Option<PathBuf>) -> Result<(), Error> {
+    if decode {
+        let phrase = read_hidden("Words: ")?;
+        let key = mnemonic::decode_key(&phrase)?;
+        eprintln!("{} ({}-bit key)", paint_err(Tone::Success, style::text("✅ Checksum OK")), key.len() * 8);
+        if key.len() == keyfile::KEY_LEN {
+            eprintln!("{} Key ID: {}", style::bullet(), paint_err(Tone::Id, container::key_id_hex(&container::key_id(&key))));
+        }
+        println!("{}", base64::encode(&key));
+        return Ok(());
+    }
+
+    let key = match key_file {
+        Some(path) => {
+            let contents = fs::read_to_string(&path).map_err(|e| Error::io(format!("Failed to read key file {}", path.display()), e))?;
+            keyfile::decode(&contents, || read_hidden("Key file passphrase: "))?
+        }
+        None => {
+            let input = read_hidden("Key (Base64): ")?;
+            base64::decode(input.trim()).map_err(|_| Error::InvalidInput("Key is not valid Base64.".into()))?
+        }
+    };
+    println!("{}", mnemonic::encode_key(&key)?);
+    Ok(())
+}
+
+fn paper(key_file: Option<PathBuf>, file_name: Option<String>, output: Option<PathBuf>) -> Result<(), Error> {
+    let secret = match key_file {
+        Some(path) => {
+            let contents = fs::read_to_string(&path).map_err(|e| Error::io(format!("Failed to read key file {}", path.display()), e))?;
+            base64::encode(keyfile::decode(&contents, || read_hidden("Key file passphrase: "))?)
+        }
+        None => {
+            let input = read_hidden("Key or secret to back up: ")?;
+            match shamir::classify(&input) {
+                (SecretKind::Key, key) => base64::encode(key),
+                (SecretKind::Text, _) => input,
+            }
+        }
+    };
+    if secret.is_empty() {
+        return Err(Error::InvalidInput("Nothing to back up.".into()));
+    }
+
+    let backup = Backup::new(&secret, file_name);
+    match output {
+        Some(path) => {
+            let format = match path.extension() {
+                Some(ext) if ext.eq_ignore_ascii_case("svg") => SheetFormat::Svg,
+                _ => SheetFormat::Text,
+            };
+            let path = claim_output(&path, collision_policy(false, false))?;
+            output::write_atomic(&path, backup.render(format)?.as_bytes(), true)
+                .map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))?;
+            println!("{} Paper backup saved to: {}", style::bullet(), paint(Tone::Value, path.display()));
+            println!("\n{} Print it, then shred the file so the key isn't left on disk.", paint(Tone::Warning, "IMPORTANT:"));
+        }
+        None => print!("{}", backup.text_sheet()?),
+    }
+    Ok(())
+}
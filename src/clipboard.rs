@@ -0,0 +1,578 @@
+// clipboard.rs
+
+use arboard::Clipboard;
+#[cfg(target_os = "linux")]
+use arboard::SetExtLinux;
+#[cfg(target_os = "macos")]
+use arboard::SetExtApple;
+#[cfg(target_os = "windows")]
+use arboard::SetExtWindows;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+
+/// Seconds a copied key stays on the clipboard before it is cleared, by default
+pub const CLEAR_SECS: u64 = 30;
+
+// Clipboards can be briefly busy (another app holding X11 ownership, a manager grabbing the copy).
+// Tests don't wait between tries, their backends answer at once.
+const ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = if cfg!(test) { Duration::ZERO } else { Duration::from_millis(150) };
+
+/// Which clipboard to use
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendChoice {
+    /// Pick one from the session: desktop, SSH, tmux
+    Auto,
+    /// The system clipboard (macOS, Windows, X11)
+    Native,
+    /// wl-copy / wl-paste on Wayland
+    Wayland,
+    /// xclip on X11
+    Xclip,
+    /// xsel on X11
+    Xsel,
+    /// tmux paste buffers, passed on to the outer terminal where tmux allows
+    Tmux,
+    /// OSC 52 terminal escape, for SSH sessions without a display
+    Osc52,
+}
+
+/// A place copied text can go
+pub trait Backend: Send {
+    /// Name shown to the user, e.g. "wl-copy"
+    fn name(&self) -> &'static str;
+    fn set(&mut self, text: &str, exclude_from_history: bool) -> Result<()>;
+    /// Reads the clipboard back, or None when this backend can't (OSC 52)
+    fn get(&mut self) -> Option<Result<String>>;
+    fn clear(&mut self) -> Result<()>;
+    /// Whether `set` can ask clipboard managers to skip the copy
+    fn hides_from_history(&self) -> bool {
+        false
+    }
+}
+
+// What a backend reports when the clipboard or its tool fails
+fn failed(e: impl std::fmt::Display) -> Error {
+    Error::Clipboard(e.to_string())
+}
+
+/// The system clipboard through arboard. It stays open while a clear is pending,
+/// as X11 drops the contents once the owning handle is closed.
+struct Native(Clipboard);
+
+impl Backend for Native {
+    fn name(&self) -> &'static str {
+        "system clipboard"
+    }
+
+    fn set(&mut self, text: &str, exclude_from_history: bool) -> Result<()> {
+        let set = self.0.set();
+        #[cfg(any(target_os = "linux", target_os = "macos", target_os = "windows"))]
+        let set = if exclude_from_history { set.exclude_from_history() } else { set };
+        #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+        let _ = exclude_from_history;
+        set.text(text.to_string()).map_err(failed)
+    }
+
+    fn get(&mut self) -> Option<Result<String>> {
+        Some(self.0.get_text().map_err(failed))
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        self.0.clear().map_err(failed)
+    }
+
+    fn hides_from_history(&self) -> bool {
+        cfg!(any(target_os = "linux", target_os = "macos", target_os = "windows"))
+    }
+}
+
+/// A clipboard driven by external tools: one command to set it (text on stdin),
+/// one to read it, one to clear it. wl-copy, xclip and xsel serve a single type per copy,
+/// so they can't offer the `x-kde-passwordManagerHint` type next to the text: with them
+/// `hides_from_history` is false, and the copy says clipboard managers weren't asked to skip it.
+struct Tool {
+    name: &'static str,
+    set: &'static [&'static str],
+    get: &'static [&'static str],
+    clear: &'static [&'static str],
+}
+
+const WL_COPY: Tool = Tool {
+    name: "wl-copy",
+    set: &["wl-copy"],
+    get: &["wl-paste", "--no-newline"],
+    clear: &["wl-copy", "--clear"],
+};
+const XCLIP: Tool = Tool {
+    name: "xclip",
+    set: &["xclip", "-selection", "clipboard", "-in"],
+    get: &["xclip", "-selection", "clipboard", "-out"],
+    clear: &["xclip", "-selection", "clipboard", "-in", "/dev/null"],
+};
+const XSEL: Tool = Tool {
+    name: "xsel",
+    set: &["xsel", "--clipboard", "--input"],
+    get: &["xsel", "--clipboard", "--output"],
+    clear: &["xsel", "--clipboard", "--clear"],
+};
+// A buffer of our own, so clearing it never deletes one the user made since.
+// -w also hands the buffer to the outer terminal's clipboard (tmux 3.2+, with set-clipboard on).
+const TMUX: Tool = Tool {
+    name: "tmux",
+    set: &["tmux", "load-buffer", "-b", TMUX_BUFFER, "-w", "-"],
+    get: &["tmux", "save-buffer", "-b", TMUX_BUFFER, "-"],
+    clear: &["tmux", "delete-buffer", "-b", TMUX_BUFFER],
+};
+const TMUX_BUFFER: &str = "crypto-bro";
+
+// Runs a tool, feeding it `input`. The setters fork a process that keeps serving the
+// clipboard, so stdout is only captured when we need it, or we'd wait for that process.
+fn run(command: &[&str], input: Option<&str>, capture: bool) -> Result<String> {
+    let (program, args) = command.split_first().ok_or_else(|| failed("Empty command."))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+        .stdout(if capture { Stdio::piped() } else { Stdio::null() })
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| failed(format!("{}: {}", program, e)))?;
+    if let Some(input) = input {
+        let mut stdin = child.stdin.take().ok_or_else(|| failed("Failed to open stdin."))?;
+        stdin.write_all(input.as_bytes()).map_err(|e| failed(format!("{}: {}", program, e)))?;
+    }
+    let output = child.wait_with_output().map_err(|e| failed(format!("{}: {}", program, e)))?;
+    if !output.status.success() {
+        return Err(failed(format!("{} failed ({})", program, output.status)));
+    }
+    String::from_utf8(output.stdout).map_err(|_| failed(format!("{} returned non-UTF-8 text", program)))
+}
+
+impl Backend for Tool {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // Offering only the hint type would leave nothing to paste, so the text goes alone
+    fn set(&mut self, text: &str, _exclude_from_history: bool) -> Result<()> {
+        run(self.set, Some(text), false).map(|_| ())
+    }
+
+    fn get(&mut self) -> Option<Result<String>> {
+        Some(run(self.get, None, true))
+    }
+
+    fn clear(&mut self) -> Result<()> {
+        run(self.clear, None, false).map(|_| ())
+    }
+}
+
+/// Asks the terminal to set its clipboard with an OSC 52 escape sequence. This works across
+/// SSH, but terminals don't let the clipboard be read back, so nothing can be verified.
+struct Osc52;
+
+impl Osc52 {
+    fn send(payload: &str) -> Result<()> {
+        let sequence = format!("\x1b]52;c;{}\x07", payload);
+        let written = match OpenOptions::new().write(true).open("/dev/tty") {
+            Ok(mut tty) => tty.write_all(sequence.as_bytes()).and_then(|_| tty.flush()),
+            Err(_) => io::stdout().write_all(sequence.as_bytes()).and_then(|_| io::stdout().flush()),
+        };
+        written.map_err(|e| failed(format!("Failed to write to the terminal: {}", e)))
+    }
+}
+
+impl Backend for Osc52 {
+    fn name(&self) -> &'static str {
+        "terminal (OSC 52)"
+    }
+
+    fn set(&mut self, text: &str, _exclude_from_history: bool) -> Result<()> {
+        Osc52::send(&base64::encode(text))
+    }
+
+    fn get(&mut self) -> Option<Result<String>> {
+        None
+    }
+
+    // Anything that isn't Base64 clears the selection
+    fn clear(&mut self) -> Result<()> {
+        Osc52::send("!")
+    }
+}
+
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+fn has_var(name: &str) -> bool {
+    env::var_os(name).is_some_and(|v| !v.is_empty())
+}
+
+fn backend(choice: BackendChoice) -> Option<Box<dyn Backend>> {
+    match choice {
+        BackendChoice::Auto => None,
+        BackendChoice::Native => Clipboard::new().ok().map(|c| Box::new(Native(c)) as Box<dyn Backend>),
+        BackendChoice::Wayland => on_path("wl-copy").then(|| Box::new(WL_COPY) as Box<dyn Backend>),
+        BackendChoice::Xclip => on_path("xclip").then(|| Box::new(XCLIP) as Box<dyn Backend>),
+        BackendChoice::Xsel => on_path("xsel").then(|| Box::new(XSEL) as Box<dyn Backend>),
+        BackendChoice::Tmux => on_path("tmux").then(|| Box::new(TMUX) as Box<dyn Backend>),
+        BackendChoice::Osc52 => Some(Box::new(Osc52)),
+    }
+}
+
+/// Backends worth trying for this session, best first. When copies should stay out of
+/// clipboard manager history, the backends that can ask for that come first.
+pub fn candidates(choice: BackendChoice, exclude_from_history: bool) -> Vec<BackendChoice> {
+    if choice != BackendChoice::Auto {
+        return vec![choice];
+    }
+    let wayland = has_var("WAYLAND_DISPLAY");
+    let x11 = has_var("DISPLAY");
+    let ssh = has_var("SSH_TTY") || has_var("SSH_CONNECTION");
+
+    let mut order = Vec::new();
+    // A forwarded display over SSH still reaches the user's desktop
+    if wayland {
+        order.push(BackendChoice::Wayland);
+    }
+    if x11 || cfg!(any(target_os = "macos", target_os = "windows")) {
+        order.extend([BackendChoice::Native, BackendChoice::Xclip, BackendChoice::Xsel]);
+    }
+    // On Wayland the native clipboard goes through XWayland, which passes the hint on
+    if exclude_from_history && wayland && x11 {
+        order.swap(0, 1);
+    }
+    if has_var("TMUX") {
+        order.push(BackendChoice::Tmux);
+    }
+    if (ssh || order.is_empty()) && io::stdout().is_terminal() {
+        order.push(BackendChoice::Osc52);
+    }
+    order
+}
+
+// Sets the text, reading it back where possible to make sure it stuck
+fn try_set(backend: &mut dyn Backend, text: &str, exclude_from_history: bool) -> Result<()> {
+    let mut last_error = String::new();
+    for attempt in 0..ATTEMPTS {
+        if attempt > 0 {
+            thread::sleep(RETRY_DELAY);
+        }
+        match backend.set(text, exclude_from_history) {
+            Ok(()) => match backend.get() {
+                None => return Ok(()),
+                Some(Ok(current)) if current == text => return Ok(()),
+                Some(Ok(_)) => last_error = "the clipboard didn't keep the text".to_string(),
+                Some(Err(e)) => last_error = e.to_string(),
+            },
+            Err(e) => last_error = e.to_string(),
+        }
+    }
+    Err(failed(format!("{}: {}", backend.name(), last_error)))
+}
+
+/// How copied keys are handled on the clipboard
+#[derive(Clone, Copy, Debug)]
+pub struct Settings {
+    pub backend: BackendChoice,
+    /// Clear the clipboard this long after copying, unless something else was copied since
+    pub clear_after: Option<Duration>,
+    /// Ask clipboard managers not to keep the copy in their history
+    pub exclude_from_history: bool,
+}
+
+/// Where a copy ended up
+pub struct Copied {
+    pub backend: &'static str,
+    /// Whether clipboard managers were asked to skip the copy
+    pub hidden_from_history: bool,
+}
+
+/// Copies text with the first backend that works, scheduling it to be cleared as the settings ask
+pub fn copy(text: &str, settings: &Settings) -> Result<Copied> {
+    let choices = candidates(settings.backend, settings.exclude_from_history);
+    if choices.is_empty() {
+        return Err(Error::Clipboard("No clipboard found: no display, no tmux, and no terminal to send OSC 52 to.".into()));
+    }
+
+    let backends = choices.into_iter().map(|choice| {
+        backend(choice).ok_or_else(|| failed(format!("{}: not available", choice.to_possible_value().unwrap().get_name())))
+    });
+    copy_with(backends, text, settings, sleep_then_clear)
+}
+
+// Copies with the first of `backends` that works, skipping the ones that couldn't be opened.
+// A clear is started with `start_timer`, which runs `clear_expired` with the copy's generation once its time is up.
+fn copy_with(
+    backends: impl IntoIterator<Item = Result<Box<dyn Backend>>>,
+    text: &str,
+    settings: &Settings,
+    start_timer: impl FnOnce(Duration, u64),
+) -> Result<Copied> {
+    let mut errors = Vec::new();
+    for backend in backends {
+        let mut backend = match backend {
+            Ok(backend) => backend,
+            Err(e) => {
+                errors.push(e.to_string());
+                continue;
+            }
+        };
+        match try_set(backend.as_mut(), text, settings.exclude_from_history) {
+            Ok(()) => {
+                let copied = Copied {
+                    backend: backend.name(),
+                    hidden_from_history: settings.exclude_from_history && backend.hides_from_history(),
+                };
+                if let Some(after) = settings.clear_after {
+                    start_timer(after, schedule_clear(backend, text, after));
+                }
+                return Ok(copied);
+            }
+            Err(e) => errors.push(e.to_string()),
+        }
+    }
+    Err(Error::Clipboard(format!("Failed to copy to the clipboard ({}).", errors.join("; "))))
+}
+
+// A copied key waiting to be cleared. Only its digest is kept, to recognise it on the clipboard.
+struct PendingClear {
+    backend: Box<dyn Backend>,
+    digest: Vec<u8>,
+    deadline: Instant,
+    // Which copy this is, so a timer only clears its own copy
+    generation: u64,
+}
+
+static PENDING_CLEAR: Mutex<Option<PendingClear>> = Mutex::new(None);
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+// Keeps the copy to be cleared in place of any earlier one, returning its generation
+fn schedule_clear(backend: Box<dyn Backend>, text: &str, after: Duration) -> u64 {
+    let digest = Sha256::digest(text.as_bytes()).to_vec();
+    let generation = GENERATION.fetch_add(1, Ordering::Relaxed) + 1;
+    let pending = PendingClear { backend, digest, deadline: Instant::now() + after, generation };
+    *PENDING_CLEAR.lock().unwrap() = Some(pending);
+    generation
+}
+
+fn sleep_then_clear(after: Duration, generation: u64) {
+    thread::spawn(move || {
+        thread::sleep(after);
+        clear_expired(generation);
+    });
+}
+
+// Clears the copy `generation` once its time is up. A later copy, even of the same key, has its own timer.
+fn clear_expired(generation: u64) -> bool {
+    let pending = {
+        let mut pending = PENDING_CLEAR.lock().unwrap();
+        if pending.as_ref().is_some_and(|p| p.generation == generation) { pending.take() } else { None }
+    };
+    clear_if_unchanged(pending)
+}
+
+// Clears the clipboard, but only if it still holds the copied key and not something copied since.
+// A clipboard that can't be read back (OSC 52) is cleared regardless. It's called with the pending
+// clear already taken out, so slow clipboard tools never hold up `countdown` in the interface.
+fn clear_if_unchanged(pending: Option<PendingClear>) -> bool {
+    let Some(mut pending) = pending else {
+        return false;
+    };
+    let unchanged = match pending.backend.get() {
+        None => true,
+        Some(Ok(current)) => Sha256::digest(current.as_bytes()).as_slice() == pending.digest.as_slice(),
+        Some(Err(_)) => false,
+    };
+    unchanged && pending.backend.clear().is_ok()
+}
+
+/// Seconds left until the copied key is cleared from the clipboard, if a clear is pending
+pub fn countdown() -> Option<u64> {
+    let pending = PENDING_CLEAR.lock().unwrap();
+    let remaining = pending.as_ref()?.deadline.saturating_duration_since(Instant::now());
+    Some(remaining.as_millis().div_ceil(1000) as u64)
+}
+
+/// Clears a pending copy right away, e.g. on exit, since the timer doesn't outlive the app.
+/// Returns whether the clipboard was cleared.
+pub fn clear_now() -> bool {
+    let pending = PENDING_CLEAR.lock().unwrap().take();
+    clear_if_unchanged(pending)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    // The pending clear is shared by the whole process, so these tests take turns
+    static SERIAL: Mutex<()> = Mutex::new(());
+
+    /// A clipboard in memory, shared with the test through the Arc
+    #[derive(Clone, Default)]
+    struct Memory(Arc<Mutex<Option<String>>>);
+
+    impl Memory {
+        fn contents(&self) -> Option<String> {
+            self.0.lock().unwrap().clone()
+        }
+
+        // Clipboard tools can be slow, so they must never run while the pending clear is locked
+        fn check_unlocked() {
+            assert!(PENDING_CLEAR.try_lock().is_ok(), "the clipboard was used with the pending clear locked");
+        }
+    }
+
+    impl Backend for Memory {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn set(&mut self, text: &str, _exclude_from_history: bool) -> Result<()> {
+            *self.0.lock().unwrap() = Some(text.to_string());
+            Ok(())
+        }
+
+        fn get(&mut self) -> Option<Result<String>> {
+            Memory::check_unlocked();
+            Some(Ok(self.contents().unwrap_or_default()))
+        }
+
+        fn clear(&mut self) -> Result<()> {
+            Memory::check_unlocked();
+            *self.0.lock().unwrap() = None;
+            Ok(())
+        }
+    }
+
+    /// Fails every time, like a tool that isn't running
+    struct Broken;
+
+    impl Backend for Broken {
+        fn name(&self) -> &'static str {
+            "broken"
+        }
+
+        fn set(&mut self, _text: &str, _exclude_from_history: bool) -> Result<()> {
+            Err(failed("no display"))
+        }
+
+        fn get(&mut self) -> Option<Result<String>> {
+            Some(Err(failed("no display")))
+        }
+
+        fn clear(&mut self) -> Result<()> {
+            Err(failed("no display"))
+        }
+    }
+
+    fn settings(clear_after: Option<Duration>) -> Settings {
+        Settings { backend: BackendChoice::Auto, clear_after, exclude_from_history: false }
+    }
+
+    fn no_timer(_: Duration, _: u64) {
+        panic!("a clear was scheduled without clear_after")
+    }
+
+    // Copies to `memory`, returning the generation its timer would clear, which the tests fire by hand
+    fn copy_to(memory: &Memory, text: &str, clear_after: Option<Duration>) -> Option<u64> {
+        let backend: Box<dyn Backend> = Box::new(memory.clone());
+        let mut timer = None;
+        let copied = copy_with([Ok(backend)], text, &settings(clear_after), |after, generation| {
+            assert_eq!(Some(after), clear_after);
+            timer = Some(generation);
+        })
+        .unwrap();
+        assert_eq!(copied.backend, "memory");
+        timer
+    }
+
+    #[test]
+    fn copies_text() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let memory = Memory::default();
+        assert_eq!(copy_to(&memory, "secret", None), None);
+        assert_eq!(memory.contents().as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn falls_back_to_the_next_backend() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let memory = Memory::default();
+        let backends: [Result<Box<dyn Backend>>; 3] =
+            [Err(failed("xclip: not available")), Ok(Box::new(Broken)), Ok(Box::new(memory.clone()))];
+        let copied = copy_with(backends, "secret", &settings(None), no_timer).unwrap();
+        assert_eq!(copied.backend, "memory");
+        assert_eq!(memory.contents().as_deref(), Some("secret"));
+    }
+
+    #[test]
+    fn fails_when_no_backend_works() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let backends: [Result<Box<dyn Backend>>; 1] = [Ok(Box::new(Broken))];
+        let error = copy_with(backends, "secret", &settings(None), no_timer).err().unwrap();
+        assert!(matches!(error, Error::Clipboard(_)));
+        assert!(error.to_string().contains("broken: no display"));
+    }
+
+    #[test]
+    fn clears_after_the_timeout() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let memory = Memory::default();
+        let timer = copy_to(&memory, "secret", Some(Duration::from_secs(30))).unwrap();
+        assert_eq!(countdown(), Some(30));
+        assert!(clear_expired(timer));
+        assert_eq!(memory.contents(), None);
+        assert_eq!(countdown(), None);
+        assert!(!clear_expired(timer));
+    }
+
+    #[test]
+    fn leaves_text_copied_since() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let memory = Memory::default();
+        let timer = copy_to(&memory, "secret", Some(Duration::from_secs(30))).unwrap();
+        memory.clone().set("something else", false).unwrap();
+        assert!(!clear_expired(timer));
+        assert_eq!(memory.contents().as_deref(), Some("something else"));
+        assert_eq!(countdown(), None);
+    }
+
+    #[test]
+    fn copying_again_restarts_the_timer() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let memory = Memory::default();
+        let first = copy_to(&memory, "secret", Some(Duration::from_secs(30))).unwrap();
+        let second = copy_to(&memory, "secret", Some(Duration::from_secs(60))).unwrap();
+        // The first copy's timer is up, but the second copy has its own
+        assert!(!clear_expired(first));
+        assert_eq!(memory.contents().as_deref(), Some("secret"));
+        assert_eq!(countdown(), Some(60));
+        assert!(clear_expired(second));
+        assert_eq!(memory.contents(), None);
+    }
+
+    #[test]
+    fn clear_now_clears_a_pending_copy() {
+        let _serial = SERIAL.lock().unwrap_or_else(|e| e.into_inner());
+        let memory = Memory::default();
+        copy_to(&memory, "secret", Some(Duration::from_secs(60)));
+        assert!(clear_now());
+        assert_eq!(memory.contents(), None);
+        assert!(!clear_now());
+    }
+}
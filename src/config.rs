@@ -0,0 +1,444 @@
+// config.rs
+
+//! Settings from the config file, normally ~/.config/crypto-bro/config.toml.
+//! `CRYPTO_BRO_<SECTION>_<KEY>` environment variables override the file, and
+//! command-line flags override both.
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::clipboard::{self, BackendChoice};
+use crate::crypto_control::WordLists;
+use crate::error::{Error, Result};
+use crate::generator::{Defaults, MAX_PASSWORD_LENGTH};
+use crate::keyfile;
+
+/// Names a config file to use instead of the default one
+pub const CONFIG_ENV: &str = "CRYPTO_BRO_CONFIG";
+/// Start of the variables that override single settings, e.g. CRYPTO_BRO_GENERATOR_PASSWORD_LENGTH
+pub const ENV_PREFIX: &str = "CRYPTO_BRO_";
+
+const SECTIONS: [&str; 6] = ["generator", "paths", "encryption", "clipboard", "display", "words"];
+
+/// A config file with every setting at its default, commented out, as written by `config init`
+pub const TEMPLATE: &str = r#"# crypto-bro settings. Uncomment a line to change it.
+# Environment variables override these, e.g. CRYPTO_BRO_GENERATOR_PASSWORD_LENGTH=24,
+# and command-line flags override both.
+
+[generator]
+# Menu option 5, and `gen password` (1 to 1024)
+# password_length = 16
+# Menu option 6
+# long_password_length = 32
+# password_symbols = true
+# 4 (random) or 7 (time-ordered)
+# uuid_version = 4
+
+[paths]
+# Where the menus look for files to encrypt or HMAC
+# data_dir = "data"
+# The others default to directories inside data_dir
+# encrypted_dir = "data/encrypted"
+# decrypted_dir = "data/decrypted"
+# keys_dir = "data/keys"
+
+[encryption]
+# PBKDF2 rounds protecting key files saved with a passphrase (100000 to 10000000)
+# kdf_rounds = 600000
+
+[clipboard]
+# Seconds before a copied key is cleared, 0 never clears it
+# timeout = 30
+# auto, native, wayland, xclip, xsel, tmux or osc52
+# backend = "auto"
+# Ask clipboard managers not to keep copied keys
+# no_history = false
+
+[display]
+# auto (only on a terminal, and not when NO_COLOR is set), always or never
+# color = "auto"
+# false writes words instead of emoji, e.g. "OK:" for ✅
+# emoji = true
+# Line-by-line menus instead of the full screen, for screen readers and logs
+# plain = false
+# "random", "none", or a banner by name: fsoc, crsbrks, or one from banner_dir (see `banners`)
+# banner = "random"
+# Extra banners, one .txt file each (default: ~/.config/crypto-bro/banners)
+# banner_dir = "/path/to/banners"
+
+[words]
+# Word lists for usernames, one word per line (default: the built-in lists)
+# descriptors = "/path/to/adjectives.txt"
+# names = "/path/to/nouns.txt"
+"#;
+
+/// Everything the config file can set. Sections and keys left out keep their defaults.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub generator: GeneratorSettings,
+    pub paths: Paths,
+    pub encryption: Encryption,
+    pub clipboard: ClipboardSettings,
+    pub display: Display,
+    pub words: Words,
+    /// The file these settings were read from, if there was one
+    #[serde(skip)]
+    pub file: Option<PathBuf>,
+    // The lists named in [words], read when the config is loaded
+    #[serde(skip)]
+    word_lists: Option<WordLists>,
+}
+
+/// `[generator]`: what `gen` and the main menu use when not told otherwise
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneratorSettings {
+    pub password_length: usize,
+    pub long_password_length: usize,
+    pub password_symbols: bool,
+    pub uuid_version: u8,
+}
+
+impl Default for GeneratorSettings {
+    fn default() -> Self {
+        let built_in = Defaults::BUILT_IN;
+        GeneratorSettings {
+            password_length: built_in.password_length,
+            long_password_length: built_in.long_password_length,
+            password_symbols: built_in.password_symbols,
+            uuid_version: built_in.uuid_version,
+        }
+    }
+}
+
+/// `[paths]`: where the menus look for files, and where commands write by default
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Paths {
+    pub data_dir: PathBuf,
+    pub encrypted_dir: Option<PathBuf>,
+    pub decrypted_dir: Option<PathBuf>,
+    pub keys_dir: Option<PathBuf>,
+}
+
+impl Default for Paths {
+    fn default() -> Self {
+        Paths { data_dir: PathBuf::from("data"), encrypted_dir: None, decrypted_dir: None, keys_dir: None }
+    }
+}
+
+impl Paths {
+    /// Encrypted files, `<data_dir>/encrypted` unless set
+    pub fn encrypted_dir(&self) -> PathBuf {
+        self.encrypted_dir.clone().unwrap_or_else(|| self.data_dir.join("encrypted"))
+    }
+
+    /// Decrypted files, `<data_dir>/decrypted` unless set
+    pub fn decrypted_dir(&self) -> PathBuf {
+        self.decrypted_dir.clone().unwrap_or_else(|| self.data_dir.join("decrypted"))
+    }
+
+    /// Key files, paper backups and signing keys, `<data_dir>/keys` unless set
+    pub fn keys_dir(&self) -> PathBuf {
+        self.keys_dir.clone().unwrap_or_else(|| self.data_dir.join("keys"))
+    }
+}
+
+/// `[encryption]`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Encryption {
+    /// PBKDF2 rounds for passphrase-protected key files
+    pub kdf_rounds: u32,
+}
+
+impl Default for Encryption {
+    fn default() -> Self {
+        Encryption { kdf_rounds: keyfile::DEFAULT_PBKDF2_ROUNDS }
+    }
+}
+
+/// `[clipboard]`: defaults for `--clipboard-timeout`, `--clipboard` and `--no-clipboard-history`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ClipboardSettings {
+    /// Seconds before a copied key is cleared, 0 never clears it
+    pub timeout: u64,
+    pub backend: BackendChoice,
+    pub no_history: bool,
+}
+
+impl Default for ClipboardSettings {
+    fn default() -> Self {
+        ClipboardSettings { timeout: clipboard::CLEAR_SECS, backend: BackendChoice::Auto, no_history: false }
+    }
+}
+
+/// `[display]`: defaults for `--color`, `--no-emoji` and `--plain`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Display {
+    pub color: ColorChoice,
+    pub emoji: bool,
+    /// Line-by-line menus instead of the full screen
+    pub plain: bool,
+    /// "random", "none" or a banner's name
+    pub banner: String,
+    pub banner_dir: Option<PathBuf>,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display { color: ColorChoice::Auto, emoji: true, plain: false, banner: "random".into(), banner_dir: None }
+    }
+}
+
+/// When output is coloured
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// On a terminal, unless NO_COLOR is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// `[words]`: word list files for usernames, instead of the built-in ones
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Words {
+    pub descriptors: Option<PathBuf>,
+    pub names: Option<PathBuf>,
+}
+
+/// The default config file: $XDG_CONFIG_HOME/crypto-bro/config.toml, else ~/.config/crypto-bro/config.toml
+pub fn default_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|v| !v.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").filter(|v| !v.is_empty()).map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("crypto-bro").join("config.toml"))
+}
+
+// A config file named with --config or $CRYPTO_BRO_CONFIG
+fn named_path(explicit: Option<&Path>) -> Option<PathBuf> {
+    explicit
+        .map(Path::to_path_buf)
+        .or_else(|| env::var_os(CONFIG_ENV).filter(|v| !v.is_empty()).map(PathBuf::from))
+}
+
+/// The config file to use: the one given, else $CRYPTO_BRO_CONFIG, else the default
+pub fn file_path(explicit: Option<&Path>) -> Option<PathBuf> {
+    named_path(explicit).or_else(default_path)
+}
+
+// Whether a section's settings fit its type, to tell which reading of a variable is meant
+fn section_fits(section: &str, table: &toml::Table) -> bool {
+    let value = toml::Value::Table(table.clone());
+    match section {
+        "generator" => value.try_into::<GeneratorSettings>().is_ok(),
+        "paths" => value.try_into::<Paths>().is_ok(),
+        "encryption" => value.try_into::<Encryption>().is_ok(),
+        "clipboard" => value.try_into::<ClipboardSettings>().is_ok(),
+        "display" => value.try_into::<Display>().is_ok(),
+        "words" => value.try_into::<Words>().is_ok(),
+        _ => true,
+    }
+}
+
+// Applies CRYPTO_BRO_<SECTION>_<KEY> variables over the file's settings. Values are
+// read as TOML where that fits the setting (numbers, true/false, quoted text), and as
+// text otherwise, so `CRYPTO_BRO_PATHS_DATA_DIR=2024` is the directory "2024".
+fn apply_env(table: &mut toml::Table, vars: impl IntoIterator<Item = (OsString, OsString)>) -> Result<()> {
+    for (name, value) in vars {
+        let (Some(name), Some(value)) = (name.to_str(), value.to_str()) else {
+            continue;
+        };
+        let Some(setting) = name.strip_prefix(ENV_PREFIX).map(str::to_ascii_lowercase) else {
+            continue;
+        };
+        let Some((section, key)) = SECTIONS
+            .iter()
+            .find_map(|s| Some((*s, setting.strip_prefix(s)?.strip_prefix('_')?)))
+        else {
+            continue;
+        };
+
+        let entry = table.entry(section).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        let Some(entry) = entry.as_table_mut() else {
+            return Err(Error::InvalidInput(format!("`{}` in the config file should be a [{}] section.", section, section)));
+        };
+        let text = toml::Value::String(value.to_string());
+        match format!("value = {}", value).parse::<toml::Table>().ok().and_then(|mut t| t.remove("value")) {
+            Some(parsed) if parsed != text => {
+                entry.insert(key.to_string(), parsed);
+                if !section_fits(section, entry) {
+                    entry.insert(key.to_string(), text);
+                }
+            }
+            _ => {
+                entry.insert(key.to_string(), text);
+            }
+        }
+    }
+    Ok(())
+}
+
+impl Config {
+    /// Reads the config file, if there is one, and applies `CRYPTO_BRO_*` variables over it.
+    /// A file named with `--config` or $CRYPTO_BRO_CONFIG has to exist, the default one doesn't.
+    pub fn load(explicit: Option<&Path>) -> Result<Config> {
+        let named = named_path(explicit);
+        let mut table = toml::Table::new();
+        let mut file = None;
+        if let Some(path) = named.clone().or_else(default_path) {
+            match fs::read_to_string(&path) {
+                Ok(text) => {
+                    table = text
+                        .parse::<toml::Table>()
+                        .map_err(|e| Error::InvalidInput(format!("Invalid config file {}: {}", path.display(), e.to_string().trim_end())))?;
+                    file = Some(path);
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound && named.is_none() => {}
+                Err(e) => return Err(Error::io(format!("Failed to read config file {}", path.display()), e)),
+            }
+        }
+        apply_env(&mut table, env::vars_os())?;
+        Config::from_table(table, file)
+    }
+
+    // Settings from the file's table with the variables applied, checked and with the word lists read
+    fn from_table(table: toml::Table, file: Option<PathBuf>) -> Result<Config> {
+        let source = match &file {
+            Some(path) => format!("{} or {}* variables", path.display(), ENV_PREFIX),
+            None => format!("{}* variables", ENV_PREFIX),
+        };
+        let mut config: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(|e| Error::InvalidInput(format!("Invalid setting in {}: {}", source, e.to_string().trim_end())))?;
+        config.validate().map_err(|e| e.context(format!("Invalid setting in {}", source)))?;
+        config.word_lists = Some(WordLists::load(config.words.descriptors.as_deref(), config.words.names.as_deref())?);
+        config.file = file;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let generator = &self.generator;
+        for length in [generator.password_length, generator.long_password_length] {
+            if !(1..=MAX_PASSWORD_LENGTH).contains(&length) {
+                return Err(Error::InvalidInput(format!("Password lengths must be 1 to {}, not {}.", MAX_PASSWORD_LENGTH, length)));
+            }
+        }
+        if ![4, 7].contains(&generator.uuid_version) {
+            return Err(Error::InvalidInput(format!("uuid_version must be 4 or 7, not {}.", generator.uuid_version)));
+        }
+        if !(keyfile::MIN_PBKDF2_ROUNDS..=keyfile::MAX_PBKDF2_ROUNDS).contains(&self.encryption.kdf_rounds) {
+            return Err(Error::InvalidInput(format!(
+                "kdf_rounds must be {} to {}, not {}.",
+                keyfile::MIN_PBKDF2_ROUNDS,
+                keyfile::MAX_PBKDF2_ROUNDS,
+                self.encryption.kdf_rounds
+            )));
+        }
+        Ok(())
+    }
+
+    /// Extra banners, `banners` next to the default config file unless set
+    pub fn banner_dir(&self) -> Option<PathBuf> {
+        self.display.banner_dir.clone().or_else(|| Some(default_path()?.parent()?.join("banners")))
+    }
+
+    /// Generator settings, for filling in what a command or menu entry leaves open
+    pub fn defaults(&self) -> Defaults {
+        Defaults {
+            password_length: self.generator.password_length,
+            long_password_length: self.generator.long_password_length,
+            password_symbols: self.generator.password_symbols,
+            uuid_version: self.generator.uuid_version,
+            words: self.word_lists.clone(),
+        }
+    }
+
+    /// The settings as TOML, with the directories filled in, for `config show`
+    pub fn to_toml(&self) -> Result<String> {
+        let mut shown = self.clone();
+        shown.paths.encrypted_dir = Some(self.paths.encrypted_dir());
+        shown.paths.decrypted_dir = Some(self.paths.decrypted_dir());
+        shown.paths.keys_dir = Some(self.paths.keys_dir());
+        shown.display.banner_dir = self.banner_dir();
+        toml::to_string_pretty(&shown).map_err(|e| Error::InvalidInput(format!("Failed to format settings: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A config from file text and CRYPTO_BRO_* variables, as `load` would read them
+    fn config(file: &str, vars: &[(&str, &str)]) -> Result<Config> {
+        let mut table = file.parse::<toml::Table>().unwrap();
+        apply_env(&mut table, vars.iter().map(|(name, value)| (OsString::from(name), OsString::from(value))))?;
+        Config::from_table(table, None)
+    }
+
+    #[test]
+    fn the_template_is_the_defaults() {
+        let template = config(TEMPLATE, &[]).unwrap();
+        assert_eq!(template.to_toml().unwrap(), Config::default().to_toml().unwrap());
+    }
+
+    #[test]
+    fn variables_override_the_file() {
+        let file = "[generator]\npassword_length = 20\nuuid_version = 7\n";
+        let config = config(file, &[("CRYPTO_BRO_GENERATOR_PASSWORD_LENGTH", "24")]).unwrap();
+        assert_eq!(config.generator.password_length, 24);
+        assert_eq!(config.generator.uuid_version, 7);
+    }
+
+    #[test]
+    fn reads_variables_by_the_setting_type() {
+        let config = config(
+            "",
+            &[
+                ("CRYPTO_BRO_CLIPBOARD_NO_HISTORY", "true"),
+                ("CRYPTO_BRO_CLIPBOARD_TIMEOUT", "5"),
+                ("CRYPTO_BRO_CLIPBOARD_BACKEND", "xsel"),
+                ("CRYPTO_BRO_DISPLAY_BANNER", "\"fsoc\""),
+                ("CRYPTO_BRO_PATHS_DATA_DIR", "2024"),
+                ("CRYPTO_BRO_PATHS_KEYS_DIR", "true"),
+            ],
+        )
+        .unwrap();
+        assert!(config.clipboard.no_history);
+        assert_eq!(config.clipboard.timeout, 5);
+        assert_eq!(config.clipboard.backend, BackendChoice::Xsel);
+        assert_eq!(config.display.banner, "fsoc");
+        assert_eq!(config.paths.data_dir, PathBuf::from("2024"));
+        assert_eq!(config.paths.keys_dir(), PathBuf::from("true"));
+    }
+
+    #[test]
+    fn ignores_other_variables() {
+        let config = config("", &[("CRYPTO_BRO_CONFIG", "elsewhere.toml"), ("CRYPTO_BRO_UNKNOWN_THING", "1")]).unwrap();
+        assert_eq!(config.to_toml().unwrap(), Config::default().to_toml().unwrap());
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_type() {
+        assert!(matches!(config("", &[("CRYPTO_BRO_GENERATOR_PASSWORD_LENGTH", "long")]), Err(Error::InvalidInput(_))));
+        assert!(matches!(config("", &[("CRYPTO_BRO_DISPLAY_EMOJI", "yes")]), Err(Error::InvalidInput(_))));
+        assert!(matches!(config("", &[("CRYPTO_BRO_GENERATOR_WIDTH", "3")]), Err(Error::InvalidInput(_))));
+        assert!(matches!(config("[generator]\npassword_length = 0\n", &[]), Err(Error::InvalidInput(_))));
+        assert!(matches!(config("", &[("CRYPTO_BRO_GENERATOR_PASSWORD_LENGTH", "1025")]), Err(Error::InvalidInput(_))));
+        assert!(matches!(config("", &[("CRYPTO_BRO_ENCRYPTION_KDF_ROUNDS", "99999")]), Err(Error::InvalidInput(_))));
+        assert!(matches!(config("", &[("CRYPTO_BRO_ENCRYPTION_KDF_ROUNDS", "10000001")]), Err(Error::InvalidInput(_))));
+        assert!(matches!(config("generator = 1\n", &[("CRYPTO_BRO_GENERATOR_PASSWORD_LENGTH", "8")]), Err(Error::InvalidInput(_))));
+    }
+}
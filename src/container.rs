@@ -0,0 +1,476 @@
+// container.rs
+
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, FileTimes};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::crypto_control::{decrypt_aes256_cbc, encrypt_aes256_cbc};
+use crate::error::{Error, Result};
+
+// Layout of a `.locked` file:
+//
+//   magic "CBRO" | version | cipher | header length (u16 LE) | header fields
+//   | IV | AES-256-CBC ciphertext | HMAC-SHA256 tag over everything before it
+//
+// The ciphertext decrypts to: metadata length (u32 LE) | metadata fields | file contents.
+// Fields are tag (u8), length (u16 LE), value. Unknown tags are skipped so newer
+// fields don't break older readers. The header is readable without the key and
+// only holds the key ID; everything else about the input stays encrypted. Files without the magic are the original
+// unauthenticated IV | ciphertext format and are still accepted.
+pub const MAGIC: &[u8; 4] = b"CBRO";
+pub const FORMAT_VERSION: u8 = 1;
+
+const CIPHER_AES256_CBC_HMAC_SHA256: u8 = 1;
+const TAG_LEN: usize = 32;
+const PREFIX_LEN: usize = MAGIC.len() + 4;
+
+const HEADER_KEY_ID: u8 = 0x01;
+
+const FIELD_SHA256: u8 = 0x01;
+const FIELD_KIND: u8 = 0x02;
+const FIELD_NAME: u8 = 0x03;
+const FIELD_SIZE: u8 = 0x04;
+const FIELD_MODE: u8 = 0x05;
+const FIELD_MODIFIED: u8 = 0x06;
+const FIELD_ACCESSED: u8 = 0x07;
+
+/// Short fingerprint identifying a key
+pub type KeyId = [u8; 8];
+
+/// What the encrypted contents are
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadKind {
+    /// A single file
+    File = 0,
+    /// A tar archive of a directory tree (see `archive`)
+    Archive = 1,
+}
+
+/// How much of a decrypted file could be checked
+#[derive(Debug, PartialEq, Eq)]
+pub enum Integrity {
+    /// Authenticated, and the contents match the SHA-256 recorded at encryption
+    Verified,
+    /// Old format with no integrity data, contents could not be checked
+    Unverified,
+}
+
+/// Original name and attributes of the encrypted input, sealed alongside its contents
+#[derive(Clone, Debug, Default)]
+pub struct FileInfo {
+    pub name: Option<String>,
+    pub size: Option<u64>,
+    /// Unix permission bits
+    pub mode: Option<u32>,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+}
+
+impl FileInfo {
+    /// Reads the name, size, permissions and timestamps of a file or directory
+    pub fn read(path: &Path) -> io::Result<FileInfo> {
+        let metadata = fs::metadata(path)?;
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            Some(metadata.permissions().mode() & 0o7777)
+        };
+        #[cfg(not(unix))]
+        let mode = None;
+
+        Ok(FileInfo {
+            name: path.canonicalize()?.file_name().map(|n| n.to_string_lossy().to_string()),
+            size: metadata.is_file().then_some(metadata.len()),
+            mode,
+            modified: metadata.modified().ok(),
+            accessed: metadata.accessed().ok(),
+        })
+    }
+
+    /// File name to restore, reduced to a bare name so a crafted header can't
+    /// point outside the output directory
+    pub fn safe_name(&self) -> Option<String> {
+        let name = self.name.as_deref()?;
+        let base = Path::new(name).file_name()?.to_string_lossy().to_string();
+        (base != "." && base != "..").then_some(base)
+    }
+
+    /// Restores permissions and timestamps onto a decrypted file.
+    /// With `private`, only the owner's permission bits are restored.
+    pub fn apply(&self, path: &Path, private: bool) -> io::Result<()> {
+        // One handle for both, opened while the file is still writable: a restored
+        // read-only mode would keep it from being opened for the timestamps
+        let file = File::options().write(true).open(path)?;
+        let mut times = FileTimes::new();
+        if let Some(modified) = self.modified {
+            times = times.set_modified(modified);
+        }
+        if let Some(accessed) = self.accessed {
+            times = times.set_accessed(accessed);
+        }
+        file.set_times(times)?;
+
+        #[cfg(unix)]
+        if let Some(mode) = self.mode {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = if private { mode & 0o700 } else { mode };
+            file.set_permissions(fs::Permissions::from_mode(mode))?;
+        }
+        #[cfg(not(unix))]
+        let _ = private;
+        Ok(())
+    }
+}
+
+fn encode_time(time: SystemTime) -> Option<[u8; 12]> {
+    let since = time.duration_since(UNIX_EPOCH).ok()?;
+    let mut out = [0u8; 12];
+    out[..8].copy_from_slice(&since.as_secs().to_le_bytes());
+    out[8..].copy_from_slice(&since.subsec_nanos().to_le_bytes());
+    Some(out)
+}
+
+fn decode_time(value: &[u8]) -> Result<SystemTime> {
+    if value.len() != 12 {
+        return Err(Error::Corrupted("Corrupted timestamp field.".into()));
+    }
+    let secs = u64::from_le_bytes(value[..8].try_into().unwrap());
+    let nanos = u32::from_le_bytes(value[8..].try_into().unwrap());
+    UNIX_EPOCH
+        .checked_add(Duration::new(secs, nanos.min(999_999_999)))
+        .ok_or_else(|| Error::Corrupted("Corrupted timestamp field.".into()))
+}
+
+pub struct Decrypted {
+    pub plaintext: Vec<u8>,
+    pub kind: PayloadKind,
+    pub info: FileInfo,
+    pub integrity: Integrity,
+}
+
+fn subkeys(key: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let hk = Hkdf::<Sha256>::new(None, key);
+    let mut enc_key = [0u8; 32];
+    let mut mac_key = [0u8; 32];
+    // Output lengths are far below HKDF's limit, expand can't fail
+    hk.expand(b"crypto-bro v1 encryption", &mut enc_key).unwrap();
+    hk.expand(b"crypto-bro v1 authentication", &mut mac_key).unwrap();
+    (enc_key, mac_key)
+}
+
+/// Fingerprint of a key, stored in the header so a wrong key can be told apart from a damaged file.
+/// It is derived through HKDF with its own label, so it reveals nothing about the key or subkeys.
+pub fn key_id(key: &[u8]) -> KeyId {
+    let mut id = [0u8; 8];
+    Hkdf::<Sha256>::new(None, key).expand(b"crypto-bro v1 key id", &mut id).unwrap();
+    id
+}
+
+pub fn key_id_hex(id: &KeyId) -> String {
+    hex::encode_upper(id)
+}
+
+fn push_field(out: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    out.push(tag);
+    out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+fn corrupted(message: &str) -> Error {
+    Error::Corrupted(message.to_string())
+}
+
+fn parse_fields(mut data: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    let mut fields = Vec::new();
+    while !data.is_empty() {
+        if data.len() < 3 {
+            return Err(corrupted("Corrupted header field."));
+        }
+        let tag = data[0];
+        let len = u16::from_le_bytes([data[1], data[2]]) as usize;
+        let value = data.get(3..3 + len).ok_or_else(|| corrupted("Corrupted header field."))?;
+        fields.push((tag, value));
+        data = &data[3 + len..];
+    }
+    Ok(fields)
+}
+
+// Header fields of a container, after checking the magic, version and cipher
+fn parse_header(data: &[u8]) -> Result<Vec<(u8, &[u8])>> {
+    if data.len() < PREFIX_LEN + TAG_LEN {
+        return Err(corrupted("File is truncated."));
+    }
+    let version = data[4];
+    if version != FORMAT_VERSION {
+        return Err(Error::UnsupportedVersion { found: version, supported: FORMAT_VERSION });
+    }
+    if data[5] != CIPHER_AES256_CBC_HMAC_SHA256 {
+        return Err(Error::UnsupportedFormat(format!("Unsupported cipher ID {}.", data[5])));
+    }
+    let header_len = u16::from_le_bytes([data[6], data[7]]) as usize;
+    if data.len() < PREFIX_LEN + header_len + TAG_LEN {
+        return Err(corrupted("File is truncated."));
+    }
+    parse_fields(&data[PREFIX_LEN..PREFIX_LEN + header_len])
+}
+
+/// Key ID recorded in a container's header, readable without the key.
+/// Files from older versions don't have one.
+pub fn stored_key_id(data: &[u8]) -> Option<KeyId> {
+    if !data.starts_with(MAGIC) {
+        return None;
+    }
+    header_key_id(&parse_header(data).ok()?)
+}
+
+fn header_key_id(header: &[(u8, &[u8])]) -> Option<KeyId> {
+    header
+        .iter()
+        .find(|(field, _)| *field == HEADER_KEY_ID)
+        .and_then(|(_, value)| (*value).try_into().ok())
+}
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    Sha256::digest(data).into()
+}
+
+/// Encrypts a file's contents (or a directory archive) and its details into an authenticated container
+pub fn seal(plaintext: &[u8], kind: PayloadKind, info: &FileInfo, key: &[u8]) -> Result<Vec<u8>> {
+    if key.len() != 32 {
+        return Err(Error::InvalidInput("Key must be 32 bytes.".into()));
+    }
+    let (enc_key, mac_key) = subkeys(key);
+
+    let mut metadata = Vec::new();
+    push_field(&mut metadata, FIELD_SHA256, &sha256(plaintext));
+    push_field(&mut metadata, FIELD_KIND, &[kind as u8]);
+    if let Some(name) = &info.name {
+        if name.len() > u16::MAX as usize {
+            return Err(Error::InvalidInput("File name is too long.".into()));
+        }
+        push_field(&mut metadata, FIELD_NAME, name.as_bytes());
+    }
+    if let Some(size) = info.size {
+        push_field(&mut metadata, FIELD_SIZE, &size.to_le_bytes());
+    }
+    if let Some(mode) = info.mode {
+        push_field(&mut metadata, FIELD_MODE, &mode.to_le_bytes());
+    }
+    if let Some(modified) = info.modified.and_then(encode_time) {
+        push_field(&mut metadata, FIELD_MODIFIED, &modified);
+    }
+    if let Some(accessed) = info.accessed.and_then(encode_time) {
+        push_field(&mut metadata, FIELD_ACCESSED, &accessed);
+    }
+
+    let mut payload = Vec::with_capacity(4 + metadata.len() + plaintext.len());
+    payload.extend_from_slice(&(metadata.len() as u32).to_le_bytes());
+    payload.extend_from_slice(&metadata);
+    payload.extend_from_slice(plaintext);
+
+    let mut header = Vec::new();
+    push_field(&mut header, HEADER_KEY_ID, &key_id(key));
+
+    let mut out = Vec::with_capacity(PREFIX_LEN + header.len() + payload.len() + 64);
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    out.push(CIPHER_AES256_CBC_HMAC_SHA256);
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(&header);
+    out.extend_from_slice(&encrypt_aes256_cbc(&payload, &enc_key)?);
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(&mac_key).unwrap();
+    mac.update(&out);
+    out.extend_from_slice(&mac.finalize().into_bytes());
+    Ok(out)
+}
+
+/// Decrypts a container, checking its authentication tag and stored checksum
+pub fn open(data: &[u8], key: &[u8]) -> Result<Decrypted> {
+    if key.len() != 32 {
+        return Err(Error::InvalidInput("Key must be 32 bytes.".into()));
+    }
+    if !data.starts_with(MAGIC) {
+        let plaintext = decrypt_aes256_cbc(data, key)?;
+        return Ok(Decrypted { plaintext, kind: PayloadKind::File, info: FileInfo::default(), integrity: Integrity::Unverified });
+    }
+
+    let header = parse_header(data)?;
+    let body_start = PREFIX_LEN + u16::from_le_bytes([data[6], data[7]]) as usize;
+
+    // A mismatched key ID means the wrong key rather than a damaged file
+    if let Some(stored) = header_key_id(&header)
+        && stored != key_id(key)
+    {
+        return Err(Error::WrongKey(format!(
+            "Wrong key: this file was encrypted with key ID {}, but the key supplied has ID {}.",
+            key_id_hex(&stored),
+            key_id_hex(&key_id(key))
+        )));
+    }
+
+    let (enc_key, mac_key) = subkeys(key);
+    let (authenticated, tag) = data.split_at(data.len() - TAG_LEN);
+    let mut mac = Hmac::<Sha256>::new_from_slice(&mac_key).unwrap();
+    mac.update(authenticated);
+    if mac.verify_slice(tag).is_err() {
+        if header_key_id(&header).is_some() {
+            return Err(corrupted("Authentication failed: the key ID matches, so the file has been modified or corrupted."));
+        }
+        // Files from before key IDs can't tell the two apart; a wrong key is the likelier cause
+        return Err(Error::WrongKey("Authentication failed: wrong key, or the file has been modified or corrupted.".into()));
+    }
+
+    let payload = decrypt_aes256_cbc(&authenticated[body_start..], &enc_key)?;
+    if payload.len() < 4 {
+        return Err(corrupted("Decrypted payload is truncated."));
+    }
+    let metadata_len = u32::from_le_bytes(payload[..4].try_into().unwrap()) as usize;
+    let metadata = payload.get(4..4 + metadata_len).ok_or_else(|| corrupted("Decrypted payload is truncated."))?;
+
+    let mut stored = None;
+    let mut kind = PayloadKind::File;
+    let mut info = FileInfo::default();
+    for (field, value) in parse_fields(metadata)? {
+        match field {
+            FIELD_SHA256 => {
+                stored = Some(<[u8; 32]>::try_from(value).map_err(|_| corrupted("Corrupted checksum field."))?);
+            }
+            FIELD_KIND => {
+                kind = match value {
+                    [0] => PayloadKind::File,
+                    [1] => PayloadKind::Archive,
+                    _ => return Err(Error::UnsupportedFormat("Unsupported payload type.".into())),
+                };
+            }
+            FIELD_NAME => {
+                info.name = Some(String::from_utf8_lossy(value).to_string());
+            }
+            FIELD_SIZE => {
+                let size = <[u8; 8]>::try_from(value).map_err(|_| corrupted("Corrupted size field."))?;
+                info.size = Some(u64::from_le_bytes(size));
+            }
+            FIELD_MODE => {
+                let mode = <[u8; 4]>::try_from(value).map_err(|_| corrupted("Corrupted permissions field."))?;
+                info.mode = Some(u32::from_le_bytes(mode));
+            }
+            FIELD_MODIFIED => info.modified = Some(decode_time(value)?),
+            FIELD_ACCESSED => info.accessed = Some(decode_time(value)?),
+            _ => {}
+        }
+    }
+    let stored = stored.ok_or_else(|| corrupted("File is missing its integrity checksum."))?;
+
+    let plaintext = payload[4 + metadata_len..].to_vec();
+    if sha256(&plaintext) != stored {
+        return Err(corrupted("Integrity check failed: decrypted contents don't match the original checksum."));
+    }
+    if kind == PayloadKind::File && info.size.is_some_and(|size| size != plaintext.len() as u64) {
+        return Err(corrupted("Integrity check failed: decrypted size doesn't match the original file."));
+    }
+    Ok(Decrypted { plaintext, kind, info, integrity: Integrity::Verified })
+}
+
+/// What `open_buffered` found alongside the contents
+pub struct Opened {
+    pub kind: PayloadKind,
+    pub info: FileInfo,
+    pub integrity: Integrity,
+}
+
+/// Encrypts everything read from `reader` and writes the container to `writer`.
+/// This is `seal` on a reader, not a stream: the whole input and the whole container are
+/// held in memory (about twice the input), as the checksum is sealed in front of the contents.
+pub fn seal_buffered(mut reader: impl Read, mut writer: impl Write, kind: PayloadKind, info: &FileInfo, key: &[u8]) -> Result<()> {
+    let mut plaintext = Vec::new();
+    reader.read_to_end(&mut plaintext)?;
+    writer.write_all(&seal(&plaintext, kind, info, key)?)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Decrypts a container read from `reader`, writing the contents to `writer`.
+/// Nothing is written unless the container authenticates and passes its integrity check,
+/// so the whole container and its contents are held in memory first (about twice the file).
+pub fn open_buffered(mut reader: impl Read, mut writer: impl Write, key: &[u8]) -> Result<Opened> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let decrypted = open(&data, key)?;
+    writer.write_all(&decrypted.plaintext)?;
+    writer.flush()?;
+    Ok(Opened { kind: decrypted.kind, info: decrypted.info, integrity: decrypted.integrity })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; 32] = [7; 32];
+    const OTHER_KEY: [u8; 32] = [8; 32];
+
+    fn info() -> FileInfo {
+        FileInfo {
+            name: Some("notes.txt".into()),
+            size: Some(11),
+            mode: Some(0o640),
+            modified: Some(UNIX_EPOCH + Duration::new(1_700_000_000, 123)),
+            accessed: None,
+        }
+    }
+
+    #[test]
+    fn seals_and_opens() {
+        let sealed = seal(b"hello world", PayloadKind::File, &info(), &KEY).unwrap();
+        assert_eq!(stored_key_id(&sealed), Some(key_id(&KEY)));
+
+        let opened = open(&sealed, &KEY).unwrap();
+        assert_eq!(opened.plaintext, b"hello world");
+        assert_eq!(opened.kind, PayloadKind::File);
+        assert_eq!(opened.integrity, Integrity::Verified);
+        assert_eq!(opened.info.name.as_deref(), Some("notes.txt"));
+        assert_eq!(opened.info.size, Some(11));
+        assert_eq!(opened.info.mode, Some(0o640));
+        assert_eq!(opened.info.modified, info().modified);
+        assert_eq!(opened.info.accessed, None);
+    }
+
+    #[test]
+    fn buffered_round_trip() {
+        let mut sealed = Vec::new();
+        seal_buffered(&b"archive bytes"[..], &mut sealed, PayloadKind::Archive, &FileInfo::default(), &KEY).unwrap();
+        let mut plaintext = Vec::new();
+        let opened = open_buffered(&sealed[..], &mut plaintext, &KEY).unwrap();
+        assert_eq!(plaintext, b"archive bytes");
+        assert_eq!(opened.kind, PayloadKind::Archive);
+    }
+
+    #[test]
+    fn detects_tampering() {
+        let sealed = seal(b"hello world", PayloadKind::File, &info(), &KEY).unwrap();
+        for at in [PREFIX_LEN + 12, sealed.len() - TAG_LEN - 1, sealed.len() - 1] {
+            let mut tampered = sealed.clone();
+            tampered[at] ^= 1;
+            assert!(matches!(open(&tampered, &KEY), Err(Error::Corrupted(_))), "byte {}", at);
+        }
+        assert!(open(&sealed[..sealed.len() - 1], &KEY).is_err());
+    }
+
+    #[test]
+    fn detects_the_wrong_key() {
+        let sealed = seal(b"hello world", PayloadKind::File, &info(), &KEY).unwrap();
+        assert!(matches!(open(&sealed, &OTHER_KEY), Err(Error::WrongKey(_))));
+        assert!(matches!(open(&sealed, &KEY[..16]), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn opens_the_original_format_unverified() {
+        let legacy = encrypt_aes256_cbc(b"old file", &KEY).unwrap();
+        let opened = open(&legacy, &KEY).unwrap();
+        assert_eq!(opened.plaintext, b"old file");
+        assert_eq!(opened.integrity, Integrity::Unverified);
+    }
+}
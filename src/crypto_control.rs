@@ -4,103 +4,164 @@ use rand::RngCore;
 use rand::Rng;
 use uuid::Uuid;
 use openssl::rand::rand_bytes;
-use openssl::symm::{encrypt, decrypt, Cipher};
+use openssl::symm::{encrypt, Cipher};
 use ring::rand::{SecureRandom, SystemRandom};
 use sha2::{Sha256, Digest};
-use base64;
-use arboard::Clipboard;
-use std::fs::File;
-use std::io::{self, BufRead};
+use std::fmt;
+use std::fs;
 use std::path::Path;
+use std::sync::{Arc, LazyLock};
+
+use crate::error::{Error, Result};
+
+// Compiled in, so generating usernames doesn't depend on the working directory.
+// Other lists can be used instead through `WordLists::load` (the `[words]` config section).
+const DESCRIPTORS: &str = include_str!("words/descriptors.txt");
+const NAMES: &str = include_str!("words/names.txt");
+
+/// Letters and digits passwords are drawn from
+pub const PASSWORD_ALPHANUMERIC: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+/// Symbols added to passwords unless left out
+pub const PASSWORD_SYMBOLS: &[u8] = b"!@#$%^&*()-_=+";
+
+// Chance of joining a username's words with an underscore
+const UNDERSCORE_CHANCE: f64 = 0.72;
+
+/// A random 256-bit key
+#[derive(Clone, PartialEq, Eq)]
+pub struct SymmetricKey([u8; 32]);
+
+impl SymmetricKey {
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// Base64, the form keys are shown and typed in
+    pub fn to_base64(&self) -> String {
+        base64::encode(self.0)
+    }
+}
+
+// Keeps keys out of debug output and logs
+impl fmt::Debug for SymmetricKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SymmetricKey(..)")
+    }
+}
 
 /// Generates a 256-bit key using OpenSSL
-pub fn generate_openssl_key() -> String {
-	println!();
-	println!("Generating...");
-	println!();
-    let mut key = vec![0u8; 32];
-    rand_bytes(&mut key).unwrap();
-    base64::encode(&key)
+pub fn generate_openssl_key() -> Result<SymmetricKey> {
+    let mut key = [0u8; 32];
+    rand_bytes(&mut key).map_err(|e| Error::Crypto(format!("OpenSSL failed to generate a key: {}", e)))?;
+    Ok(SymmetricKey(key))
 }
 
 /// Generates a 256-bit key using Ring
-pub fn generate_ring_key() -> String {
-	println!();
-	println!("Generating...");
-	println!();
+pub fn generate_ring_key() -> Result<SymmetricKey> {
     let rng = SystemRandom::new();
-    let mut key = vec![0u8; 32];
-    rng.fill(&mut key).unwrap();
-    base64::encode(&key)
+    let mut key = [0u8; 32];
+    rng.fill(&mut key).map_err(|_| Error::Crypto("Ring failed to generate a key.".into()))?;
+    Ok(SymmetricKey(key))
 }
 
 /// Generates a UUID
-pub fn generate_uuid() -> String {
-	println!();
-	println!("Generating...");
-	println!();
-    Uuid::new_v4().to_string()
+pub fn generate_uuid() -> Uuid {
+    Uuid::new_v4()
+}
+
+/// Generates a time-ordered UUID, which sorts by creation time (e.g. for database keys)
+pub fn generate_uuid_v7() -> Uuid {
+    Uuid::now_v7()
 }
 
 /// Generates an API key using Base64
 pub fn generate_api_key() -> String {
-	println!();
-	println!("Generating...");
-	println!();
     let mut random_bytes = vec![0u8; 32];
     rand::thread_rng().fill_bytes(&mut random_bytes);
     base64::encode(random_bytes)
 }
 
-/// Generates a 16 or 32 bit password
-pub fn generate_password(length: u32) -> String {
-    // Define possible characters for the password
-    let charset = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ\
-                   abcdefghijklmnopqrstuvwxyz\
-                   0123456789!@#$%^&*()-_=+";
-    let mut rng = rand::thread_rng();
+/// Characters a password is drawn from
+pub fn password_charset(symbols: bool) -> Vec<u8> {
+    let mut charset = PASSWORD_ALPHANUMERIC.to_vec();
+    if symbols {
+        charset.extend_from_slice(PASSWORD_SYMBOLS);
+    }
+    charset
+}
 
-    // Choose the password length based on the input (16 or 32)
-    let password_length = match length {
-        16 | 32 => length as usize,
-        _ => {
-            println!("\x1b[91mInvalid password length! Please choose 16 or 32.\x1b[0m");
-            return String::new();  // Return an empty string for invalid input
-        }
-    };
+/// Generates a password of the given length from letters, digits and optionally symbols
+pub fn generate_password(length: usize, symbols: bool) -> Result<String> {
+    let charset = password_charset(symbols);
+    if length == 0 {
+        return Err(Error::InvalidInput("Password length must be at least 1.".into()));
+    }
+    let mut rng = rand::thread_rng();
 
     // Generate random password
-    let password: Vec<u8> = (0..password_length)
-        .map(|_| charset[rng.next_u32() as usize % charset.len()] as u8)
+    let password: Vec<u8> = (0..length)
+        .map(|_| charset[rng.gen_range(0..charset.len())])
         .collect();
 
     // Convert the password bytes to a string
-    password.iter().map(|&byte| byte as char).collect::<String>()
+    Ok(password.iter().map(|&byte| byte as char).collect::<String>())
 }
 
-// Loads word lists
-fn load_word_list(path: &str) -> Vec<String> {
-    let path = Path::new(path);
-    let file = File::open(&path).expect("Could not open file");
-    let reader = io::BufReader::new(file);
-    
-    reader
-        .lines()
-        .filter_map(|line| line.ok())
-        .collect::<Vec<String>>()
+/// The adjectives and nouns usernames are made from. Cloning shares the lists.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WordLists {
+    descriptors: Arc<[String]>,
+    names: Arc<[String]>,
 }
 
-// Uses word lists to gen a username
-pub fn generate_username() -> String {
-    let descriptors = load_word_list("src/words/descriptors.txt");
-    let names = load_word_list("src/words/names.txt");
+static BUILT_IN_WORDS: LazyLock<WordLists> =
+    LazyLock::new(|| WordLists { descriptors: parse_word_list(DESCRIPTORS).into(), names: parse_word_list(NAMES).into() });
 
+impl WordLists {
+    /// Lists of words, neither of which may be empty
+    pub fn new(descriptors: Vec<String>, names: Vec<String>) -> Result<WordLists> {
+        if descriptors.is_empty() || names.is_empty() {
+            return Err(Error::InvalidInput("Word lists need at least one word each.".into()));
+        }
+        Ok(WordLists { descriptors: descriptors.into(), names: names.into() })
+    }
+
+    /// The lists compiled in from src/words
+    pub fn built_in() -> WordLists {
+        BUILT_IN_WORDS.clone()
+    }
+
+    /// Reads word list files, one word per line, using the built-in list for any not given
+    pub fn load(descriptors: Option<&Path>, names: Option<&Path>) -> Result<WordLists> {
+        let built_in = WordLists::built_in();
+        let read = |path: Option<&Path>, built_in: Arc<[String]>| -> Result<Arc<[String]>> {
+            let Some(path) = path else {
+                return Ok(built_in);
+            };
+            let list = fs::read_to_string(path).map_err(|e| Error::io(format!("Failed to read word list {}", path.display()), e))?;
+            let words = parse_word_list(&list);
+            if words.is_empty() {
+                return Err(Error::InvalidInput(format!("Word list {} has no words.", path.display())));
+            }
+            Ok(words.into())
+        };
+        Ok(WordLists { descriptors: read(descriptors, built_in.descriptors)?, names: read(names, built_in.names)? })
+    }
+}
+
+// One word per line, blank lines skipped
+fn parse_word_list(list: &str) -> Vec<String> {
+    list.lines().map(str::trim).filter(|l| !l.is_empty()).map(str::to_string).collect()
+}
+
+// Uses word lists to gen a username
+pub fn generate_username(words: &WordLists) -> String {
     let mut rng = rand::thread_rng();
 
     // Randomly select descriptors and names, occasionally adding underscores
-    let descriptor = descriptors[rng.gen_range(0..descriptors.len())].clone();
-    let name = names[rng.gen_range(0..names.len())].clone();
-	let add_underscore: bool = rng.gen_bool(0.72); 
+    let descriptor = &words.descriptors[rng.gen_range(0..words.descriptors.len())];
+    let name = &words.names[rng.gen_range(0..words.names.len())];
+	let add_underscore: bool = rng.gen_bool(UNDERSCORE_CHANCE); 
 
     // Combine them into a username
 	if add_underscore {
@@ -110,33 +171,24 @@ pub fn generate_username() -> String {
     }
 }
 
-/// Copies text to clipboard
-pub fn copy_to_clipboard(text: &str) -> bool {
-    if let Ok(mut clipboard) = Clipboard::new() {
-        if clipboard.set_text(text.to_string()).is_ok() {
-            // Verify that the clipboard has the correct content
-			// DO NOT REMOVE - COPY FUNCTION IS NOT EFFECTIVE WITHOUT IT
-            if let Ok(clip_content) = clipboard.get_text() {
-                if clip_content == text {
-                    return true;
-                }
-            }
-        }
-    }
-    false
+/// Bits of entropy in a generated username: the two word choices plus the underscore coin flip
+pub fn username_entropy(words: &WordLists) -> f64 {
+    let words = (words.descriptors.len() * words.names.len()) as f64;
+    let p = UNDERSCORE_CHANCE;
+    words.log2() - p * p.log2() - (1.0 - p) * (1.0 - p).log2()
 }
 
 /// Encrypts a plaintext using AES-256-CBC with the given 32-byte key
-pub fn encrypt_aes256_cbc(plaintext: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+pub fn encrypt_aes256_cbc(plaintext: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     if key.len() != 32 {
-        return Err("Key must be 32 bytes.".into());
+        return Err(Error::InvalidInput("Key must be 32 bytes.".into()));
     }
     let mut iv = [0u8; 16];
     rand::thread_rng().fill(&mut iv);
 
     
     let mut ciphertext = encrypt(Cipher::aes_256_cbc(), key, Some(&iv), plaintext)
-        .map_err(|e| format!("Encryption failed: {}", e))?;
+        .map_err(|e| Error::Crypto(format!("Encryption failed: {}", e)))?;
     
     let mut result = iv.to_vec();
     result.append(&mut ciphertext);
@@ -144,21 +196,53 @@ pub fn encrypt_aes256_cbc(plaintext: &[u8], key: &[u8]) -> Result<Vec<u8>, Strin
 }
 
 /// Decrypts AES-256-CBC ciphertext using the provided 32-byte key
-pub fn decrypt_aes256_cbc(ciphertext: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+/// Bad padding means the wrong key or damaged data; unauthenticated CBC can't tell which.
+pub fn decrypt_aes256_cbc(ciphertext: &[u8], key: &[u8]) -> Result<Vec<u8>> {
     if key.len() != 32 {
-        return Err("Key must be 32 bytes.".into());
+        return Err(Error::InvalidInput("Key must be 32 bytes.".into()));
     }
     if ciphertext.len() < 16 {
-        return Err("Ciphertext too short.".into());
+        return Err(Error::Corrupted("Ciphertext too short.".into()));
     }
 
     let (iv, data) = ciphertext.split_at(16);
     openssl::symm::decrypt(Cipher::aes_256_cbc(), key, Some(iv), data)
-        .map_err(|e| format!("Decryption failed: {}", e))
+        .map_err(|e| Error::Corrupted(format!("Decryption failed (wrong key or damaged data): {}", e)))
 }
 
 pub fn sha256_checksum(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(data);
     hex::encode(hasher.finalize())
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(list: &[&str]) -> Vec<String> {
+        list.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn usernames_come_from_the_lists() {
+        let lists = WordLists::new(words(&["quiet"]), words(&["fox"])).unwrap();
+        for _ in 0..20 {
+            let username = generate_username(&lists);
+            assert!(username == "quietfox" || username == "quiet_fox", "{}", username);
+        }
+    }
+
+    #[test]
+    fn rejects_empty_lists() {
+        assert!(matches!(WordLists::new(Vec::new(), words(&["fox"])), Err(Error::InvalidInput(_))));
+        assert!(matches!(WordLists::new(words(&["quiet"]), Vec::new()), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn built_in_lists_have_words() {
+        let lists = WordLists::built_in();
+        assert!(!lists.descriptors.is_empty() && !lists.names.is_empty());
+        assert_eq!(WordLists::load(None, None).unwrap(), lists);
+        assert!(username_entropy(&lists) > 1.0);
+    }
+}
@@ -0,0 +1,101 @@
+// error.rs
+
+use std::fmt;
+use std::io;
+
+/// Errors returned by the library
+#[derive(Debug)]
+pub enum Error {
+    /// Reading or writing failed
+    Io(io::Error),
+    /// The key doesn't belong to this data
+    WrongKey(String),
+    /// Data is damaged, truncated or has been tampered with
+    Corrupted(String),
+    /// Encrypted with a newer (or unknown) container format version
+    UnsupportedVersion { found: u8, supported: u8 },
+    /// Uses a cipher or payload type this build doesn't know
+    UnsupportedFormat(String),
+    /// A parameter or piece of input that can't be used
+    InvalidInput(String),
+    /// The crypto library or random number generator failed
+    Crypto(String),
+    /// No clipboard could be used
+    Clipboard(String),
+    /// The user backed out, nothing was changed
+    Cancelled(String),
+}
+
+/// Result type of the library
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::WrongKey(message)
+            | Error::Corrupted(message)
+            | Error::UnsupportedFormat(message)
+            | Error::InvalidInput(message)
+            | Error::Crypto(message)
+            | Error::Clipboard(message)
+            | Error::Cancelled(message) => write!(f, "{}", message),
+            Error::UnsupportedVersion { found, supported } => {
+                write!(f, "Unsupported format version {} (this build reads version {}).", found, supported)
+            }
+        }
+    }
+}
+
+impl Error {
+    /// An I/O error with what was being done, e.g. "Failed to read notes.txt"
+    pub fn io(context: impl fmt::Display, source: io::Error) -> Error {
+        Error::Io(io::Error::new(source.kind(), format!("{}: {}", context, source)))
+    }
+
+    /// Prefixes the message, e.g. with the file it's about, keeping the kind of error
+    pub fn context(self, context: impl fmt::Display) -> Error {
+        let with = |message: String| format!("{}: {}", context, message);
+        match self {
+            Error::Io(e) => Error::io(context, e),
+            Error::WrongKey(message) => Error::WrongKey(with(message)),
+            Error::Corrupted(message) => Error::Corrupted(with(message)),
+            Error::UnsupportedFormat(message) => Error::UnsupportedFormat(with(message)),
+            Error::InvalidInput(message) => Error::InvalidInput(with(message)),
+            Error::Crypto(message) => Error::Crypto(with(message)),
+            Error::Clipboard(message) => Error::Clipboard(with(message)),
+            Error::Cancelled(message) => Error::Cancelled(with(message)),
+            version @ Error::UnsupportedVersion { .. } => Error::UnsupportedFormat(with(version.to_string())),
+        }
+    }
+
+    /// Process exit code for this kind of error, so scripts can tell failures apart.
+    /// 2 matches the code clap uses for bad arguments.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Io(_) => 1,
+            Error::InvalidInput(_) => 2,
+            Error::WrongKey(_) => 3,
+            Error::Corrupted(_) => 4,
+            Error::UnsupportedVersion { .. } | Error::UnsupportedFormat(_) => 5,
+            Error::Crypto(_) => 6,
+            Error::Clipboard(_) => 7,
+            Error::Cancelled(_) => 8,
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
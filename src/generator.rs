@@ -0,0 +1,465 @@
+// generator.rs
+
+//! Every key type the app can generate, in one registry. The main menu, `-k`,
+//! the `gen` subcommands and their flags, the options forms, batch mode and JSON
+//! output are all built from `GENERATORS`, each generator's `parameters` and its
+//! `menu_entries`, so adding a generator here is enough to offer it everywhere.
+//! A setting no generator had before also needs a field in `Options`.
+
+use crate::crypto_control::{self, WordLists};
+use crate::error::{Error, Result};
+
+/// What values a setting takes, and its default
+#[derive(Clone, Copy, Debug)]
+pub enum Kind {
+    /// A whole number from `min` to `max`
+    Number { min: usize, max: usize, default: usize },
+    /// On or off. `off_flag` and `off_help` make the command line flag that turns it off.
+    Toggle { default: bool, off_flag: &'static str, off_help: &'static str },
+    /// One of a few fixed values
+    Choice { choices: &'static [&'static str], default: &'static str },
+}
+
+/// A value of a setting, of its parameter's kind
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    Number(usize),
+    Toggle(bool),
+    Choice(&'static str),
+}
+
+/// A setting a generator takes. The options form, the `gen` flags and help text are made from it.
+pub struct Parameter {
+    /// Name in forms and the long command line flag, e.g. "length" for `--length`
+    pub name: &'static str,
+    /// Short command line flag, e.g. 'l' for `-l`
+    pub short: Option<char>,
+    pub description: &'static str,
+    pub kind: Kind,
+    /// Reads the setting from options, `None` if it is left to the default
+    pub read: fn(&Options) -> Option<Setting>,
+    /// Stores a value of the parameter's kind in options
+    pub write: fn(&mut Options, Setting),
+}
+
+impl Parameter {
+    /// The setting in `options`, or the parameter's default
+    pub fn value(&self, options: &Options) -> Setting {
+        (self.read)(options).unwrap_or(match self.kind {
+            Kind::Number { default, .. } => Setting::Number(default),
+            Kind::Toggle { default, .. } => Setting::Toggle(default),
+            Kind::Choice { default, .. } => Setting::Choice(default),
+        })
+    }
+
+    pub fn set(&self, options: &mut Options, value: Setting) {
+        (self.write)(options, value)
+    }
+}
+
+/// Settings for a generator. Each generator only reads the ones in its `parameters`,
+/// and uses its own default for any left as `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Length in characters
+    pub length: Option<usize>,
+    /// Whether passwords include symbols
+    pub symbols: Option<bool>,
+    /// UUID version
+    pub version: Option<u8>,
+    /// Word lists for usernames
+    pub words: Option<WordLists>,
+}
+
+impl Options {
+    /// Every setting left to the generator's default
+    pub const DEFAULT: Options = Options { length: None, symbols: None, version: None, words: None };
+}
+
+/// Values for the settings a command or menu entry leaves open, e.g. from the config file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Defaults {
+    pub password_length: usize,
+    /// Length of the menu's second, longer password
+    pub long_password_length: usize,
+    pub password_symbols: bool,
+    pub uuid_version: u8,
+    /// Word lists for usernames, the built-in ones if `None`
+    pub words: Option<WordLists>,
+}
+
+impl Defaults {
+    /// The defaults without a config file
+    pub const BUILT_IN: Defaults = Defaults {
+        password_length: DEFAULT_PASSWORD_LENGTH,
+        long_password_length: 32,
+        password_symbols: true,
+        uuid_version: 4,
+        words: None,
+    };
+
+    /// Fills in every setting left as `None`
+    pub fn fill(&self, options: Options) -> Options {
+        Options {
+            length: options.length.or(Some(self.password_length)),
+            symbols: options.symbols.or(Some(self.password_symbols)),
+            version: options.version.or(Some(self.uuid_version)),
+            words: options.words.or_else(|| self.words.clone()),
+        }
+    }
+}
+
+/// Generates one kind of key, ID or credential
+pub trait Generator: Sync {
+    /// Short name for the command line and JSON output, e.g. "password"
+    fn name(&self) -> &'static str;
+    /// Name shown in the menu, e.g. "Password"
+    fn title(&self) -> &'static str;
+    /// One line for `gen --help`
+    fn about(&self) -> &'static str;
+    /// What a value looks like with these options, e.g. "16-characters"
+    fn description(&self, options: &Options) -> String;
+    /// Settings it takes
+    fn parameters(&self) -> &'static [Parameter] {
+        &[]
+    }
+    /// Main menu entries, as options for the configured defaults to fill in
+    fn menu_entries(&self, _defaults: &Defaults) -> Vec<Options> {
+        vec![Options::DEFAULT]
+    }
+    /// Bits of entropy in one value
+    fn entropy(&self, options: &Options) -> f64;
+    /// Whether values are 256-bit keys in Base64, which can also be written as words
+    fn is_key(&self) -> bool {
+        false
+    }
+    fn generate(&self, options: &Options) -> Result<String>;
+}
+
+pub struct OpenSslKey;
+pub struct RingKey;
+pub struct Uuid;
+pub struct ApiKey;
+pub struct Password;
+pub struct Username;
+
+const DEFAULT_PASSWORD_LENGTH: usize = 16;
+/// Longest password the options form, `gen --length` and the config file allow
+pub const MAX_PASSWORD_LENGTH: usize = 1024;
+const UUID_VERSIONS: &[&str] = &["4", "7"];
+
+const PASSWORD_PARAMETERS: &[Parameter] = &[
+    Parameter {
+        name: "length",
+        short: Some('l'),
+        description: "Number of characters",
+        kind: Kind::Number { min: 1, max: MAX_PASSWORD_LENGTH, default: DEFAULT_PASSWORD_LENGTH },
+        read: |options| options.length.map(Setting::Number),
+        write: |options, value| {
+            if let Setting::Number(length) = value {
+                options.length = Some(length);
+            }
+        },
+    },
+    Parameter {
+        name: "symbols",
+        short: None,
+        description: "Include symbols as well as letters and digits",
+        kind: Kind::Toggle { default: true, off_flag: "no-symbols", off_help: "Only use letters and digits" },
+        read: |options| options.symbols.map(Setting::Toggle),
+        write: |options, value| {
+            if let Setting::Toggle(on) = value {
+                options.symbols = Some(on);
+            }
+        },
+    },
+];
+
+const UUID_PARAMETERS: &[Parameter] = &[Parameter {
+    name: "version",
+    short: Some('v'),
+    description: "4 (random) or 7 (sorts by creation time)",
+    kind: Kind::Choice { choices: UUID_VERSIONS, default: "4" },
+    read: |options| {
+        let version = options.version?.to_string();
+        UUID_VERSIONS.iter().find(|v| **v == version).map(|v| Setting::Choice(v))
+    },
+    write: |options, value| {
+        if let Setting::Choice(version) = value {
+            options.version = version.parse().ok();
+        }
+    },
+}];
+
+impl Generator for OpenSslKey {
+    fn name(&self) -> &'static str {
+        "openssl"
+    }
+    fn title(&self) -> &'static str {
+        "OpenSSL Cryptographic Key"
+    }
+    fn about(&self) -> &'static str {
+        "256-bit key from OpenSSL's random generator, Base64 encoded"
+    }
+    fn description(&self, _: &Options) -> String {
+        "Base64 encoded, 256-bit".into()
+    }
+    fn entropy(&self, _: &Options) -> f64 {
+        256.0
+    }
+    fn is_key(&self) -> bool {
+        true
+    }
+    fn generate(&self, _: &Options) -> Result<String> {
+        Ok(crypto_control::generate_openssl_key()?.to_base64())
+    }
+}
+
+impl Generator for RingKey {
+    fn name(&self) -> &'static str {
+        "ring"
+    }
+    fn title(&self) -> &'static str {
+        "Ring Cryptographic Key"
+    }
+    fn about(&self) -> &'static str {
+        "256-bit key from Ring's random generator, Base64 encoded"
+    }
+    fn description(&self, _: &Options) -> String {
+        "Base64 encoded, 256-bit".into()
+    }
+    fn entropy(&self, _: &Options) -> f64 {
+        256.0
+    }
+    fn is_key(&self) -> bool {
+        true
+    }
+    fn generate(&self, _: &Options) -> Result<String> {
+        Ok(crypto_control::generate_ring_key()?.to_base64())
+    }
+}
+
+impl Generator for Uuid {
+    fn name(&self) -> &'static str {
+        "uuid"
+    }
+    fn title(&self) -> &'static str {
+        "UUID"
+    }
+    fn about(&self) -> &'static str {
+        "UUID, random (v4) or time-ordered (v7)"
+    }
+    fn description(&self, options: &Options) -> String {
+        format!("v{} format", options.version.unwrap_or(4))
+    }
+    fn parameters(&self) -> &'static [Parameter] {
+        UUID_PARAMETERS
+    }
+    // v4 fixes 6 of the 128 bits; v7 also spends 48 on the timestamp
+    fn entropy(&self, options: &Options) -> f64 {
+        match options.version.unwrap_or(4) {
+            7 => 74.0,
+            _ => 122.0,
+        }
+    }
+    fn generate(&self, options: &Options) -> Result<String> {
+        match options.version.unwrap_or(4) {
+            4 => Ok(crypto_control::generate_uuid().to_string()),
+            7 => Ok(crypto_control::generate_uuid_v7().to_string()),
+            version => Err(Error::InvalidInput(format!("UUID version {} is not supported (use 4 or 7).", version))),
+        }
+    }
+}
+
+impl Generator for ApiKey {
+    fn name(&self) -> &'static str {
+        "api-key"
+    }
+    fn title(&self) -> &'static str {
+        "API Key"
+    }
+    fn about(&self) -> &'static str {
+        "256-bit API key, Base64 encoded"
+    }
+    fn description(&self, _: &Options) -> String {
+        "Base64 encoded, 256-bit".into()
+    }
+    fn entropy(&self, _: &Options) -> f64 {
+        256.0
+    }
+    fn generate(&self, _: &Options) -> Result<String> {
+        Ok(crypto_control::generate_api_key())
+    }
+}
+
+impl Generator for Password {
+    fn name(&self) -> &'static str {
+        "password"
+    }
+    fn title(&self) -> &'static str {
+        "Password"
+    }
+    fn about(&self) -> &'static str {
+        "Random password of letters, digits and symbols"
+    }
+    fn description(&self, options: &Options) -> String {
+        format!("{}-characters", options.length.unwrap_or(DEFAULT_PASSWORD_LENGTH))
+    }
+    fn parameters(&self) -> &'static [Parameter] {
+        PASSWORD_PARAMETERS
+    }
+    // The menu also offers a longer password
+    fn menu_entries(&self, defaults: &Defaults) -> Vec<Options> {
+        vec![Options::DEFAULT, Options { length: Some(defaults.long_password_length), ..Options::DEFAULT }]
+    }
+    fn entropy(&self, options: &Options) -> f64 {
+        let length = options.length.unwrap_or(DEFAULT_PASSWORD_LENGTH);
+        let charset = crypto_control::password_charset(options.symbols.unwrap_or(true));
+        length as f64 * (charset.len() as f64).log2()
+    }
+    fn generate(&self, options: &Options) -> Result<String> {
+        crypto_control::generate_password(options.length.unwrap_or(DEFAULT_PASSWORD_LENGTH), options.symbols.unwrap_or(true))
+    }
+}
+
+impl Generator for Username {
+    fn name(&self) -> &'static str {
+        "username"
+    }
+    fn title(&self) -> &'static str {
+        "Username"
+    }
+    fn about(&self) -> &'static str {
+        "Username made from the built-in word lists"
+    }
+    fn description(&self, _: &Options) -> String {
+        "Word list generated".into()
+    }
+    fn entropy(&self, options: &Options) -> f64 {
+        crypto_control::username_entropy(&words(options))
+    }
+    fn generate(&self, options: &Options) -> Result<String> {
+        Ok(crypto_control::generate_username(&words(options)))
+    }
+}
+
+fn words(options: &Options) -> WordLists {
+    options.words.clone().unwrap_or_else(WordLists::built_in)
+}
+
+/// Every generator, in menu order
+pub static GENERATORS: &[&dyn Generator] = &[&OpenSslKey, &RingKey, &Uuid, &ApiKey, &Password, &Username];
+
+/// Looks a generator up by its short name
+pub fn find(name: &str) -> Option<&'static dyn Generator> {
+    GENERATORS.iter().copied().find(|g| g.name() == name)
+}
+
+/// A numbered main menu entry, also selectable with `-k`: a generator with fixed options
+#[derive(Clone)]
+pub struct Preset {
+    pub number: u32,
+    pub generator: &'static dyn Generator,
+    pub options: Options,
+}
+
+/// The numbered entries of the main menu: every generator's menu entries, in registry
+/// order, with their settings filled in from `defaults`
+pub fn presets(defaults: &Defaults) -> Vec<Preset> {
+    GENERATORS
+        .iter()
+        .flat_map(|&generator| generator.menu_entries(defaults).into_iter().map(move |options| (generator, options)))
+        .zip(1..)
+        .map(|((generator, options), number)| Preset { number, generator, options: defaults.fill(options) })
+        .collect()
+}
+
+/// The preset with this menu number
+pub fn preset(number: u32, defaults: &Defaults) -> Result<Preset> {
+    let presets = presets(defaults);
+    presets.iter().find(|p| p.number == number).cloned().ok_or_else(|| {
+        Error::InvalidInput(format!("Unknown key type {} (choose 1 to {}).", number, presets.len()))
+    })
+}
+
+impl Preset {
+    /// e.g. "Password (16-characters)"
+    pub fn label(&self) -> String {
+        format!("{} ({})", self.generator.title(), self.generator.description(&self.options))
+    }
+
+    pub fn generate(&self) -> Result<Generated> {
+        generate(self.generator, &self.options)
+    }
+}
+
+/// A generated value and what produced it
+pub struct Generated {
+    pub generator: &'static dyn Generator,
+    pub value: String,
+    pub entropy: f64,
+}
+
+/// Runs a generator, recording its entropy alongside the value
+pub fn generate(generator: &'static dyn Generator, options: &Options) -> Result<Generated> {
+    Ok(Generated { generator, value: generator.generate(options)?, entropy: generator.entropy(options) })
+}
+
+impl Generated {
+    /// One JSON object, e.g. `{"generator":"uuid","value":"...","entropy_bits":122.0}`
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"generator\":\"{}\",\"value\":\"{}\",\"entropy_bits\":{:.1}}}",
+            json_escape(self.generator.name()),
+            json_escape(&self.value),
+            self.entropy
+        )
+    }
+}
+
+fn json_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_come_from_the_registry() {
+        let presets = presets(&Defaults::BUILT_IN);
+        assert_eq!(presets.iter().map(|p| p.number).collect::<Vec<_>>(), (1..=presets.len() as u32).collect::<Vec<_>>());
+        for generator in GENERATORS {
+            assert!(presets.iter().any(|p| p.generator.name() == generator.name()), "{}", generator.name());
+        }
+        let long = preset(6, &Defaults::BUILT_IN).unwrap();
+        assert_eq!((long.generator.name(), long.options.length), ("password", Some(32)));
+    }
+
+    #[test]
+    fn parameters_read_back_what_they_write() {
+        for generator in GENERATORS {
+            for parameter in generator.parameters() {
+                let value = match parameter.kind {
+                    Kind::Number { max, .. } => Setting::Number(max),
+                    Kind::Toggle { default, .. } => Setting::Toggle(!default),
+                    Kind::Choice { choices, .. } => Setting::Choice(choices[choices.len() - 1]),
+                };
+                let mut options = Options::DEFAULT;
+                assert_eq!(parameter.value(&options), parameter.value(&Defaults::BUILT_IN.fill(Options::DEFAULT)));
+                parameter.set(&mut options, value);
+                assert_eq!(parameter.value(&options), value, "{} {}", generator.name(), parameter.name);
+                assert!(generator.generate(&options).is_ok());
+            }
+        }
+    }
+}
@@ -0,0 +1,190 @@
+// keyfile.rs
+
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::RngCore;
+use sha2::Sha256;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::container;
+use crate::error::{Error, Result};
+use crate::mnemonic;
+use crate::output;
+
+pub const KEY_EXTENSION: &str = "key";
+pub const KEY_LEN: usize = 32;
+
+const HEADER: &str = "# crypto-bro decryption key";
+const WRAPPED_HEADER: &str = "# crypto-bro decryption key (passphrase protected)";
+const WRAPPED_PREFIX: &str = "pbkdf2-sha256";
+/// PBKDF2 rounds for passphrase-protected key files, unless configured otherwise
+pub const DEFAULT_PBKDF2_ROUNDS: u32 = 600_000;
+/// Fewest rounds accepted for key files
+pub const MIN_PBKDF2_ROUNDS: u32 = 100_000;
+/// Most rounds accepted for key files. Reading a file costs what it asks for,
+/// so an edited or corrupted count must not keep the app busy for hours.
+pub const MAX_PBKDF2_ROUNDS: u32 = 10_000_000;
+
+// Passphrase-derived key that seals the decryption key with ChaCha20-Poly1305
+fn wrapping_cipher(passphrase: &str, salt: &[u8], rounds: u32) -> ChaCha20Poly1305 {
+    let mut wrapping_key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, rounds, &mut wrapping_key);
+    ChaCha20Poly1305::new(Key::from_slice(&wrapping_key))
+}
+
+/// Encodes a key for saving, wrapped with a passphrase when one is given.
+/// `rounds` is the PBKDF2 cost of the wrapping, stored in the file so it can change later.
+pub fn to_file_string(key: &[u8], passphrase: Option<&str>, rounds: u32) -> Result<String> {
+    let id = container::key_id_hex(&container::key_id(key));
+    let Some(passphrase) = passphrase else {
+        return Ok(format!("{}\n# key ID: {}\n{}\n", HEADER, id, base64::encode(key)));
+    };
+
+    let mut salt = [0u8; 16];
+    let mut nonce = [0u8; 12];
+    rand::thread_rng().fill_bytes(&mut salt);
+    rand::thread_rng().fill_bytes(&mut nonce);
+    if !(MIN_PBKDF2_ROUNDS..=MAX_PBKDF2_ROUNDS).contains(&rounds) {
+        return Err(Error::InvalidInput(format!("Key files need {} to {} PBKDF2 rounds.", MIN_PBKDF2_ROUNDS, MAX_PBKDF2_ROUNDS)));
+    }
+    let sealed = wrapping_cipher(passphrase, &salt, rounds)
+        .encrypt(Nonce::from_slice(&nonce), key)
+        .map_err(|_| Error::Crypto("Failed to wrap key.".into()))?;
+
+    Ok(format!(
+        "{}\n# key ID: {}\n{}:{}:{}:{}:{}\n",
+        WRAPPED_HEADER,
+        id,
+        WRAPPED_PREFIX,
+        rounds,
+        base64::encode(salt),
+        base64::encode(nonce),
+        base64::encode(sealed)
+    ))
+}
+
+/// Whether a file looks like a key file written by this app
+pub fn is_key_file(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|c| c.starts_with(HEADER))
+}
+
+/// Reads a key from a key file's contents, a bare Base64 key or a BIP39 mnemonic.
+/// The passphrase is only asked for when the key is wrapped.
+pub fn decode(contents: &str, passphrase: impl FnOnce() -> io::Result<String>) -> Result<Vec<u8>> {
+    let lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect::<Vec<_>>();
+    let line = lines.first().ok_or_else(|| Error::InvalidInput("Key is empty.".into()))?;
+
+    // Written-down words may have been copied over several lines
+    if line.split_whitespace().nth(1).is_some() {
+        let key = mnemonic::decode_key(&lines.join(" "))?;
+        if key.len() != KEY_LEN {
+            return Err(Error::InvalidInput("These words are a shorter key, file keys are 24 words.".into()));
+        }
+        return Ok(key);
+    }
+
+    let key = match line.strip_prefix(WRAPPED_PREFIX).and_then(|rest| rest.strip_prefix(':')) {
+        Some(wrapped) => {
+            let fields = wrapped.split(':').collect::<Vec<_>>();
+            let [rounds, salt, nonce, sealed] = fields[..] else {
+                return Err(Error::UnsupportedFormat("Unsupported key file format.".into()));
+            };
+            let rounds = rounds.parse::<u32>().map_err(|_| Error::UnsupportedFormat("Unsupported key file format.".into()))?;
+            if !(MIN_PBKDF2_ROUNDS..=MAX_PBKDF2_ROUNDS).contains(&rounds) {
+                return Err(Error::UnsupportedFormat(format!(
+                    "Key file asks for {} PBKDF2 rounds, outside {} to {}.",
+                    rounds, MIN_PBKDF2_ROUNDS, MAX_PBKDF2_ROUNDS
+                )));
+            }
+            let decode_field = |f: &str| base64::decode(f).map_err(|_| Error::InvalidInput("Key file is not valid Base64.".into()));
+            let (salt, nonce, sealed) = (decode_field(salt)?, decode_field(nonce)?, decode_field(sealed)?);
+            if nonce.len() != 12 {
+                return Err(Error::UnsupportedFormat("Unsupported key file format.".into()));
+            }
+
+            let pass = passphrase().map_err(|e| Error::io("Failed to read passphrase", e))?;
+            wrapping_cipher(&pass, &salt, rounds)
+                .decrypt(Nonce::from_slice(&nonce), sealed.as_slice())
+                .map_err(|_| Error::WrongKey("Wrong passphrase or corrupted key file.".into()))?
+        }
+        None => base64::decode(line).map_err(|_| Error::InvalidInput("Invalid key format or length.".into()))?,
+    };
+
+    if key.len() != KEY_LEN {
+        return Err(Error::InvalidInput("Invalid key format or length.".into()));
+    }
+    Ok(key)
+}
+
+/// Saves a key owner-only (0600), wrapped with a passphrase when one is given
+pub fn save(path: &Path, key: &[u8], passphrase: Option<&str>, rounds: u32) -> Result<()> {
+    let contents = to_file_string(key, passphrase, rounds)?;
+    output::write_atomic(path, contents.as_bytes(), true)
+        .map_err(|e| Error::io(format!("Failed to write {}", path.display()), e))
+}
+
+/// Reads everything from an inherited file descriptor, e.g. `--key-fd 3` with `3< key.txt`
+pub fn read_fd(fd: u32) -> io::Result<String> {
+    fs::read_to_string(format!("/dev/fd/{}", fd))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::no_passphrase;
+
+    const KEY: [u8; KEY_LEN] = [9; KEY_LEN];
+
+    #[test]
+    fn plain_keys_round_trip() {
+        let contents = to_file_string(&KEY, None, DEFAULT_PBKDF2_ROUNDS).unwrap();
+        assert!(contents.starts_with(HEADER));
+        assert_eq!(decode(&contents, no_passphrase).unwrap(), KEY);
+        assert_eq!(decode(&base64::encode(KEY), no_passphrase).unwrap(), KEY);
+    }
+
+    #[test]
+    fn wrapped_keys_need_the_passphrase() {
+        let contents = to_file_string(&KEY, Some("hunter2"), MIN_PBKDF2_ROUNDS).unwrap();
+        assert!(!contents.contains(&base64::encode(KEY)));
+        assert_eq!(decode(&contents, || Ok("hunter2".into())).unwrap(), KEY);
+        assert!(matches!(decode(&contents, || Ok("hunter3".into())), Err(Error::WrongKey(_))));
+    }
+
+    #[test]
+    fn rejects_rounds_out_of_range() {
+        assert!(matches!(to_file_string(&KEY, Some("hunter2"), MIN_PBKDF2_ROUNDS - 1), Err(Error::InvalidInput(_))));
+        assert!(matches!(to_file_string(&KEY, Some("hunter2"), MAX_PBKDF2_ROUNDS + 1), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn refuses_files_asking_for_rounds_out_of_range() {
+        let contents = to_file_string(&KEY, Some("hunter2"), MIN_PBKDF2_ROUNDS).unwrap();
+        let written = format!("{}:{}:", WRAPPED_PREFIX, MIN_PBKDF2_ROUNDS);
+        for rounds in [0, 1, MIN_PBKDF2_ROUNDS - 1, MAX_PBKDF2_ROUNDS + 1, u32::MAX] {
+            let edited = contents.replace(&written, &format!("{}:{}:", WRAPPED_PREFIX, rounds));
+            // Refused before the passphrase is asked for, let alone derived
+            assert!(matches!(decode(&edited, no_passphrase), Err(Error::UnsupportedFormat(_))), "{}", rounds);
+        }
+    }
+
+    #[test]
+    fn reads_24_words_but_not_shorter_keys() {
+        let words = mnemonic::encode_key(&KEY).unwrap();
+        assert_eq!(decode(&words, no_passphrase).unwrap(), KEY);
+        let short = mnemonic::encode_key(&KEY[..16]).unwrap();
+        assert!(matches!(decode(&short, no_passphrase), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn rejects_keys_of_the_wrong_length() {
+        assert!(matches!(decode(&base64::encode(&KEY[..16]), no_passphrase), Err(Error::InvalidInput(_))));
+        assert!(matches!(decode("# only a comment\n", no_passphrase), Err(Error::InvalidInput(_))));
+    }
+}
@@ -0,0 +1,25 @@
+// lib.rs
+
+//! Key generation, file encryption and the other tools behind the crypto-bro app,
+//! usable without its menus. Nothing here prints; failures come back as `Error`.
+
+pub mod archive;
+pub mod checksum;
+pub mod clipboard;
+pub mod config;
+pub mod container;
+pub mod crypto_control;
+pub mod error;
+pub mod generator;
+pub mod keyfile;
+pub mod mac;
+pub mod mnemonic;
+pub mod output;
+pub mod paper;
+pub mod shamir;
+pub mod shred;
+pub mod signing;
+#[cfg(test)]
+mod testing;
+
+pub use error::{Error, Result};
@@ -1,275 +1,743 @@
 // src/lunchbox.rs
 
 use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use rand::Rng;
-use sha2::{Sha256};
-use std::fs::File;
-use std::io::Read;
+use uuid::Uuid;
+
+use key_gen_app::Error;
+use key_gen_app::archive;
+use key_gen_app::config::Config;
+use key_gen_app::container::{self, Decrypted, FileInfo, Integrity, PayloadKind};
+use key_gen_app::crypto_control::sha256_checksum;
+use key_gen_app::mac::{self, HmacAlgorithm};
+use key_gen_app::mnemonic;
+use key_gen_app::output::{self, Collision};
+use key_gen_app::paper::{Backup, SheetFormat};
+use key_gen_app::keyfile;
+use crate::style::{self, paint, Tone};
+use crate::tui::{say, Item, Next, Pick, Ui};
+use key_gen_app::shamir::{self, SecretKind, Share, ShareEncoding};
+use key_gen_app::shred;
 
-use crate::crypto_control::{encrypt_aes256_cbc, decrypt_aes256_cbc, sha256_checksum};
+// mKmYyAFP8QMZDQPKCzAJsPwjXt7dpG0BrgbN7RhxQ+M=  key
+// 4f02a09796005d68cb3735884e26cdf8e540c011f48765976f6432cb24b24443 chksum
 
-pub fn load_file(path: &str) -> Result<String, std::io::Error> {
-    fs::read_to_string(path)
+pub fn max_allowed_file_size() -> u64 {
+    if let Ok(meminfo) = fs::read_to_string("/proc/meminfo") {
+        for line in meminfo.lines() {
+            if line.starts_with("MemAvailable:")
+                && let Some(kb_str) = line.split_whitespace().nth(1)
+                && let Ok(kb) = kb_str.parse::<u64>()
+            {
+                return (kb * 1024) / 4; // ¼ of free RAM in bytes
+            }
+        }
+    }
+    5 * 1024 * 1024 // fallback: 5 MiB
 }
 
-pub fn save_file(path: &str, data: &[u8]) -> Result<(), std::io::Error> {
-    fs::write(path, data)
+// The end of every flow: back to the main menu, or quit
+fn done(ui: &mut Ui) -> Result<Next, Error> {
+    match ui.choose("", &[Item::new('m', "Main menu"), Item::new('q', "Exit the app")], 'm')? {
+        Some('q') => Ok(Next::Quit),
+        _ => Ok(Next::Menu),
+    }
 }
 
-// mKmYyAFP8QMZDQPKCzAJsPwjXt7dpG0BrgbN7RhxQ+M=  key
-// 4f02a09796005d68cb3735884e26cdf8e540c011f48765976f6432cb24b24443 chksum
+// Asks where an output goes: the usual directory, next to what it was made from, or another one.
+// `None` if the user backed out.
+fn choose_output_dir(ui: &mut Ui, usual: &Path, source: &Path) -> Result<Option<PathBuf>, Error> {
+    let beside = source.parent().map(Path::to_path_buf).unwrap_or_default();
+    let items = [
+        Item::new('d', format!("{}/", usual.display())),
+        Item::new('n', "Next to the original").detail(format!("{}/", beside.display())),
+        Item::new('o', "Pick another directory"),
+        Item::new('t', "Type a directory").detail("created if missing"),
+    ];
+    match ui.choose("Save to:", &items, 'd')? {
+        Some('d') => Ok(Some(usual.to_path_buf())),
+        Some('n') => Ok(Some(beside)),
+        Some('o') => ui.browse("Output directory:", usual, Pick::Dir, &[], |_| true),
+        Some('t') => ui.input_path("Output directory:", &std::env::current_dir()?),
+        _ => Ok(None),
+    }
+}
 
-fn max_allowed_file_size() -> u64 {
-    if let Ok(meminfo) = fs::read_to_string("/proc/meminfo") {
-        for line in meminfo.lines() {
-            if line.starts_with("MemAvailable:") {
-                if let Some(kb_str) = line.split_whitespace().nth(1) {
-                    if let Ok(kb) = kb_str.parse::<u64>() {
-                        return (kb * 1024) / 4; // ¼ of free RAM in bytes
-                    }
+pub fn show_encrypt_menu(ui: &mut Ui, config: &Config) {
+    ui.screen("📦 Encrypt");
+    say!(ui, "WARNING:\n\n  The file browser starts in {}/, but any file or directory you can read may be picked.\n  Encrypted files go to {}/ unless you choose another place, and existing files are never replaced without asking.\n\n  This program is experimental. Using filetypes other than .env or .txt may have unpredicatable results.",
+        config.paths.data_dir.display(), config.paths.encrypted_dir().display());
+    say!(ui, "\nMax file size allowed: {:.2} MiB\n", max_allowed_file_size() as f64 / 1024.0 / 1024.0);
+}
+
+pub fn encrypt_menu_loop(ui: &mut Ui, config: &Config) -> Result<Next, Error> {
+    let paths = &config.paths;
+    show_encrypt_menu(ui, config);
+    let items = [
+        Item::new('e', "Encrypt file"),
+        Item::new('d', "Encrypt directory").detail("single archive"),
+        Item::new('m', "Main menu"),
+        Item::new('q', "Exit the app"),
+    ];
+    loop {
+        let choice = match ui.choose("", &items, 'e')? {
+            Some(c @ ('e' | 'd')) => c,
+            Some('q') => return Ok(Next::Quit),
+            _ => return Ok(Next::Menu),
+        };
+
+        let (selected, kind) = if choice == 'e' {
+            (ui.browse("File to encrypt:", &paths.data_dir, Pick::File, &[], |_| true)?, PayloadKind::File)
+        } else {
+            let reserved = [paths.encrypted_dir(), paths.decrypted_dir(), paths.keys_dir()]
+                .iter()
+                .filter_map(|r| fs::canonicalize(r).ok())
+                .collect::<Vec<_>>();
+            // The output directories usually live inside the data directory too, they are never offered for packing
+            let include = |p: &Path| !fs::canonicalize(p).is_ok_and(|p| reserved.contains(&p));
+            (ui.browse("Directory to encrypt:", &paths.data_dir, Pick::Dir, &[], include)?, PayloadKind::Archive)
+        };
+        let Some(file_path) = selected else {
+            say!(ui, "{}", paint(Tone::Error, "No valid file selected."));
+            continue;
+        };
+
+        let file_size = match kind {
+            PayloadKind::File => fs::metadata(&file_path).map(|m| m.len()),
+            PayloadKind::Archive => archive::dir_size(&file_path),
+        };
+        let file_size = match file_size {
+            Ok(size) => size,
+            Err(e) => {
+                say!(ui, "{}", paint(Tone::Error, format_args!("Failed to read {}: {}", file_path.display(), e)));
+                continue;
+            }
+        };
+        let max_size = max_allowed_file_size();
+
+        if file_size > max_size {
+            say!(ui, "{}", paint(Tone::Error, format_args!("File too large. Limit is {:.2} MiB", max_size as f64 / 1024.0 / 1024.0)));
+            continue;
+        }
+
+        let mut key_bytes = generate_key();
+        let key_items = [Item::new('a', "Accept key"), Item::new('r', "Regenerate"), Item::new('q', "Cancel")];
+        loop {
+            say!(ui, "\n{}\n{}", paint(Tone::Success, "Generated Decryption Key:"), paint(Tone::Value, base64::encode(key_bytes)));
+            say!(ui, "As words: {}", paint(Tone::Value, mnemonic::encode_key(&key_bytes)?));
+            let key_id = container::key_id(&key_bytes);
+            say!(ui, "Key ID: {} (stored in the encrypted file to identify this key)", paint(Tone::Id, container::key_id_hex(&key_id)));
+            match ui.choose("", &key_items, 'a')? {
+                Some('a') => break,
+                Some('r') => key_bytes = generate_key(),
+                _ => return Ok(Next::Menu),
+            }
+        }
+        let decryption_key = base64::encode(key_bytes);
+
+        let obfuscate = ui.confirm("Hide the original name on disk with a random ID? The real name stays sealed inside.")?;
+        let shred_original = ui.confirm("Shred the original once the encrypted file is verified?")?;
+
+        let read = match kind {
+            PayloadKind::File => fs::read(&file_path),
+            PayloadKind::Archive => archive::pack_dir(&file_path),
+        }
+        .and_then(|plaintext| Ok((plaintext, FileInfo::read(&file_path)?)));
+        let (plaintext, info) = match read {
+            Ok(read) => read,
+            Err(e) => {
+                say!(ui, "{}", paint(Tone::Error, format_args!("Failed to read {}: {}", file_path.display(), e)));
+                continue;
+            }
+        };
+        let encrypted = match container::seal(&plaintext, kind, &info, &key_bytes) {
+            Ok(encrypted) => encrypted,
+            Err(e) => {
+                say!(ui, "{}", paint(Tone::Error, format_args!("Encryption failed: {}", e)));
+                continue;
+            }
+        };
+
+        let Some(out_dir) = choose_output_dir(ui, &paths.encrypted_dir(), &file_path)? else {
+            say!(ui, "{}", paint(Tone::Warning, "Cancelled, nothing was written."));
+            continue;
+        };
+        let out_path = out_dir.join(locked_file_name(info.name.as_deref().unwrap_or("unnamed"), obfuscate));
+        let Some(out_path) = output::resolve(&out_path, Collision::Ask, |path, alternative| ui.ask_collision(path, alternative))? else {
+            say!(ui, "{}", paint(Tone::Warning, "Cancelled, nothing was written."));
+            continue;
+        };
+        if let Err(e) = output::write_atomic(&out_path, &encrypted, false) {
+            say!(ui, "{}", paint(Tone::Error, format_args!("Failed to write encrypted file: {}", e)));
+            continue;
+        }
+
+        let checksum = sha256_checksum(&plaintext);
+
+        say!(ui, "\n{}", paint(Tone::Success, style::text("✅ Encryption Complete")));
+        say!(ui, "{} Encrypted file saved to: {}", style::bullet(), paint(Tone::Value, out_path.display()));
+        match kind {
+            PayloadKind::File => say!(ui, "{} Original file checksum (SHA-256): {}", style::bullet(), paint(Tone::Id, checksum)),
+            PayloadKind::Archive => say!(ui, "{} Archive checksum (SHA-256): {}", style::bullet(), paint(Tone::Id, checksum)),
+        }
+        say!(ui, "\n  The checksum is sealed inside the encrypted file and verified automatically on decryption.");
+        say!(ui, "\n{} Keep your decryption key safe.\n", paint(Tone::Warning, "IMPORTANT:"));
+        let locked_name = out_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        ui.record(format!("Encrypted {}", locked_name), Some(decryption_key.clone()));
+
+        if ui.confirm(&format!("Save the key to a file in {}/?", paths.keys_dir().display()))? {
+            save_key_file(ui, config, &out_path, &key_bytes)?;
+        }
+
+        if ui.confirm("Create a printable paper backup of the key?")? {
+            save_paper_backup(ui, config, &decryption_key, Some(locked_name.clone()), &locked_name)?;
+        }
+
+        if ui.confirm("Split the key into recovery shares for several people?")? {
+            split_secret(ui, SecretKind::Key, &key_bytes)?;
+        }
+
+        if shred_original {
+            match shred::shred_source(&file_path, &out_path, &plaintext, &key_bytes) {
+                Ok(count) => {
+                    say!(ui, "{} Verified the encrypted file and shredded the original ({} file(s)).", style::bullet(), count);
+                    say!(ui, "{} {}\n", paint(Tone::Warning, "NOTE:"), shred::CAVEAT);
                 }
+                Err(e) => say!(ui, "{}\n", paint(Tone::Error, format_args!("Original kept: {}", e))),
             }
         }
+
+        return done(ui);
+    }
+}
+
+/// Name for an encrypted output: the original name, or a random ID that reveals nothing about it
+pub fn locked_file_name(original: &str, obfuscate: bool) -> String {
+    if obfuscate {
+        format!("{}.locked", Uuid::new_v4().simple())
+    } else {
+        format!("{}.locked", original)
     }
-    5 * 1024 * 1024 // fallback: 5 MiB
 }
 
-pub fn show_encrypt_menu() {
-    println!("\n\n\n  📦 Pack Mode Selected");
-    println!("\n\n  WARNING:\n\n  Please ensure the target file to encrypt has been placed in /crypto-bro/data directory.\n  Any duplicate namespace in data/encrypted will be overwritten. \n\n  This program is experimental. Using filetypes other than .env or .txt may have unpredicatable results.");
-    println!("\n  Max file size allowed: {:.2} MiB\n", max_allowed_file_size() as f64 / 1024.0 / 1024.0);
-    println!("\n  Options:");
-    println!("    [e] Encrypt file");
-    println!("    [m] Main menu");
-    println!("    [q] Exit the app\n");
-    print!("\nType option (e, m, q) and press [Enter]: ");
+/// Name to decrypt to: the name sealed in the file, or for old files the
+/// encrypted file's name without its `.locked` extension
+pub fn restored_file_name(info: &FileInfo, locked_path: &Path) -> String {
+    info.safe_name().unwrap_or_else(|| {
+        let name = locked_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        match name.strip_suffix(".locked") {
+            Some(stem) if !stem.is_empty() => stem.to_string(),
+            _ => format!("{}.decrypted", name),
+        }
+    })
+}
+
+pub fn generate_key() -> [u8; 32] {
+    let mut rng = rand::thread_rng();
+    let mut key_bytes = [0u8; 32];
+    rng.fill(&mut key_bytes);
+    key_bytes
+}
+
+pub fn show_decrypt_menu(ui: &mut Ui, config: &Config) {
+    ui.screen("📥 Decrypt");
+    say!(ui, "WARNING:\n\n  The file browser starts in {}/, but an encrypted file may be picked from anywhere.\n  Decrypted files go to {}/ unless you choose another place, and nothing is replaced without asking.\n  Decrypted files are only readable by you (permissions 0600).",
+        config.paths.encrypted_dir().display(), config.paths.decrypted_dir().display());
+    say!(ui, "\n  For files secured by this program only. If a file was encrypted with a different tool or parameters,\n  this decryptor likely won’t work.");
+    say!(ui, "\nMax file size allowed: {:.2} MiB", max_allowed_file_size() as f64 / 1024.0 / 1024.0);
+    say!(ui, "\nBe prepared to provide your recovery key on the next screen.\n");
+}
+
+// Saves the key in the keys directory, named after the encrypted file, optionally behind a passphrase
+fn save_key_file(ui: &mut Ui, config: &Config, encrypted_path: &Path, key: &[u8]) -> Result<(), Error> {
+    let name = encrypted_path.file_name().unwrap_or_default().to_string_lossy();
+    let key_path = config.paths.keys_dir().join(format!("{}.{}", name, keyfile::KEY_EXTENSION));
+    let Some(key_path) = output::resolve(&key_path, Collision::Ask, |path, alternative| ui.ask_collision(path, alternative))? else {
+        say!(ui, "{}\n", paint(Tone::Warning, "Key file not saved."));
+        return Ok(());
+    };
+
+    let passphrase = match ui.new_passphrase("Passphrase to protect the key file (leave empty for none):")? {
+        Some(pass) if pass.is_empty() => None,
+        Some(pass) => Some(pass),
+        None => {
+            say!(ui, "{}\n", paint(Tone::Warning, "Key file not saved."));
+            return Ok(());
+        }
+    };
+    match keyfile::save(&key_path, key, passphrase.as_deref(), config.encryption.kdf_rounds) {
+        Ok(()) => say!(ui, "{} Key file saved to: {} (readable only by you)\n", style::bullet(), paint(Tone::Value, key_path.display())),
+        Err(e) => say!(ui, "{}\n", paint(Tone::Error, e)),
+    }
+    Ok(())
+}
+
+/// Asks for a sheet format and writes a paper backup of a key to the keys directory (owner-only)
+pub fn save_paper_backup(ui: &mut Ui, config: &Config, secret: &str, file_name: Option<String>, base_name: &str) -> Result<(), Error> {
+    let items = [
+        Item::new('1', "Text"),
+        Item::new('2', "SVG").detail("A4, prints with a scannable QR code"),
+        Item::new('3', "Both"),
+    ];
+    let formats = match ui.choose("Paper backup format:", &items, '1')? {
+        Some('1') => vec![SheetFormat::Text],
+        Some('2') => vec![SheetFormat::Svg],
+        Some('3') => vec![SheetFormat::Text, SheetFormat::Svg],
+        _ => {
+            say!(ui, "{}", paint(Tone::Warning, "Paper backup not saved."));
+            return Ok(());
+        }
+    };
+
+    let backup = Backup::new(secret, file_name);
+    for format in formats {
+        let path = config.paths.keys_dir().join(format!("{}.backup.{}", base_name, format.extension()));
+        let Some(path) = output::resolve(&path, Collision::Ask, |path, alternative| ui.ask_collision(path, alternative))? else {
+            say!(ui, "{}", paint(Tone::Warning, "Paper backup not saved."));
+            continue;
+        };
+        let written = backup
+            .render(format)
+            .and_then(|sheet| output::write_atomic(&path, sheet.as_bytes(), true).map_err(Error::from));
+        match written {
+            Ok(()) => say!(ui, "{} Paper backup saved to: {} (readable only by you)", style::bullet(), paint(Tone::Value, path.display())),
+            Err(e) => say!(ui, "{}", paint(Tone::Error, format_args!("Failed to save paper backup: {}", e))),
+        }
+    }
+    say!(ui, "\n{} Print it, then shred the file (`shred` command) so the key isn't left on disk.\n", paint(Tone::Warning, "IMPORTANT:"));
+    Ok(())
 }
 
-pub fn encrypt_menu_loop() {
+// Asks for a key without echoing it, or lets the user pick a saved key file instead
+fn read_decryption_key(ui: &mut Ui, config: &Config) -> Result<Option<Vec<u8>>, Error> {
+    let Some(input) = ui.input_hidden("Enter your decryption key (Base64 or words), or press [Enter] to use a key file:")? else {
+        return Ok(None);
+    };
+
+    let contents = if input.trim().is_empty() {
+        let Some(path) = ui.browse("Key file:", &config.paths.keys_dir(), Pick::File, &[], keyfile::is_key_file)? else {
+            return Ok(None);
+        };
+        match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(e) => {
+                say!(ui, "{}", paint(Tone::Error, format_args!("Failed to read key file: {}", e)));
+                return Ok(None);
+            }
+        }
+    } else {
+        input
+    };
+
+    match keyfile::decode(&contents, || ui.read_passphrase("Key file passphrase:")) {
+        Ok(key) => Ok(Some(key)),
+        Err(e) => {
+            say!(ui, "{}", paint(Tone::Error, e));
+            Ok(None)
+        }
+    }
+}
+
+// Shows the original details sealed in an encrypted file, if it has any
+fn print_file_info(ui: &mut Ui, info: &FileInfo) {
+    if let Some(name) = &info.name {
+        say!(ui, "{} Original name: {}", style::bullet(), paint(Tone::Value, name));
+    }
+    if let Some(size) = info.size {
+        say!(ui, "{} Original size: {} bytes", style::bullet(), size);
+    }
+    if let Some(mode) = info.mode {
+        say!(ui, "{} Original permissions: {:o}", style::bullet(), mode);
+    }
+    if let Some(modified) = info.modified {
+        say!(ui, "{} Last modified: {}", style::bullet(), humantime::format_rfc3339_seconds(modified));
+    }
+}
+
+// Selects an encrypted file, asks for its key and decrypts it, reporting any failure
+fn open_selected_file(ui: &mut Ui, config: &Config) -> Result<Option<(PathBuf, Decrypted)>, Error> {
+    let Some(file_path) = ui.browse("Encrypted file:", &config.paths.encrypted_dir(), Pick::File, &["locked"], |_| true)? else {
+        say!(ui, "{}", paint(Tone::Error, "No valid file selected."));
+        return Ok(None);
+    };
+
+    let file_size = match fs::metadata(&file_path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) => {
+            say!(ui, "{}", paint(Tone::Error, format_args!("Failed to read {}: {}", file_path.display(), e)));
+            return Ok(None);
+        }
+    };
+    let max_size = max_allowed_file_size();
+
+    if file_size > max_size {
+        say!(ui, "{}", paint(Tone::Error, format_args!("File too large. Limit is {:.2} MiB", max_size as f64 / 1024.0 / 1024.0)));
+        return Ok(None);
+    }
+
+    let ciphertext = match fs::read(&file_path) {
+        Ok(c) => c,
+        Err(_) => {
+            say!(ui, "{}", paint(Tone::Error, "Failed to read encrypted file."));
+            return Ok(None);
+        }
+    };
+    if let Some(key_id) = container::stored_key_id(&ciphertext) {
+        say!(ui, "\n{} This file was encrypted with key ID {}", style::bullet(), paint(Tone::Id, container::key_id_hex(&key_id)));
+    }
+
+    let Some(key_bytes) = read_decryption_key(ui, config)? else {
+        return Ok(None);
+    };
+
+    match container::open(&ciphertext, &key_bytes) {
+        Ok(d) => Ok(Some((file_path, d))),
+        Err(e) => {
+            say!(ui, "{}", paint(Tone::Error, format_args!("{} Decryption failed: {}", style::text("❌"), e)));
+            Ok(None)
+        }
+    }
+}
+
+pub fn decrypt_menu_loop(ui: &mut Ui, config: &Config) -> Result<Next, Error> {
+    let decrypted_dir = config.paths.decrypted_dir();
+    show_decrypt_menu(ui, config);
+    let items = [
+        Item::new('d', "Decrypt file"),
+        Item::new('l', "List archive contents"),
+        Item::new('s', format!("Shred everything in {}/", decrypted_dir.display())),
+        Item::new('m', "Main menu"),
+        Item::new('q', "Exit the app"),
+    ];
     loop {
-        show_encrypt_menu();
-        io::stdout().flush().unwrap();
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        let choice = input.trim();
-
-        match choice {
-            "e" => {
-                let file_path = match select_file("data") {
-                    Some(f) => f,
-                    None => {
-                        println!("\x1b[91mNo valid file selected.\x1b[0m");
-                        continue;
+        match ui.choose("", &items, 'd')? {
+            Some('d') => {
+                let Some((file_path, decrypted)) = open_selected_file(ui, config)? else {
+                    continue;
+                };
+
+                let Some(out_dir) = choose_output_dir(ui, &decrypted_dir, &file_path)? else {
+                    say!(ui, "{}", paint(Tone::Warning, "Cancelled, nothing was written."));
+                    continue;
+                };
+                let out_path = match decrypted.kind {
+                    PayloadKind::File => {
+                        let out_path = out_dir.join(restored_file_name(&decrypted.info, &file_path));
+                        let Some(out_path) = output::resolve(&out_path, Collision::Ask, |path, alternative| ui.ask_collision(path, alternative))? else {
+                            say!(ui, "{}", paint(Tone::Warning, "Cancelled, nothing was written."));
+                            continue;
+                        };
+
+                        if let Err(e) = output::write_atomic(&out_path, &decrypted.plaintext, true) {
+                            say!(ui, "{}", paint(Tone::Error, format_args!("Failed to write decrypted file: {}", e)));
+                            continue;
+                        }
+                        if let Err(e) = decrypted.info.apply(&out_path, true) {
+                            say!(ui, "{}", paint(Tone::Warning, format_args!("Could not restore file permissions or timestamps: {}", e)));
+                        }
+                        out_path.display().to_string()
+                    }
+                    PayloadKind::Archive => {
+                        let target = out_dir.join(restored_file_name(&decrypted.info, &file_path));
+                        let Some(target) = output::resolve(&target, Collision::Ask, |path, alternative| ui.ask_collision(path, alternative))? else {
+                            say!(ui, "{}", paint(Tone::Warning, "Cancelled, nothing was written."));
+                            continue;
+                        };
+
+                        if let Err(e) = archive::unpack(&decrypted.plaintext, &target, true) {
+                            say!(ui, "{}", paint(Tone::Error, format_args!("Failed to extract archive: {}", e)));
+                            continue;
+                        }
+                        format!("{}/", target.display())
                     }
                 };
 
-                let metadata = fs::metadata(&file_path).expect("Could not read file metadata");
-                let file_size = metadata.len();
-                let max_size = max_allowed_file_size();
+                let checksum = sha256_checksum(&decrypted.plaintext);
 
-                if file_size > max_size {
-                    println!("\x1b[91mFile too large. Limit is {:.2} MiB\x1b[0m", max_size as f64 / 1024.0 / 1024.0);
-                    continue;
+                say!(ui, "\n{}", paint(Tone::Success, style::text("✅ Decryption Complete")));
+                match decrypted.kind {
+                    PayloadKind::File => {
+                        say!(ui, "{} Decrypted file saved to: {}", style::bullet(), paint(Tone::Value, out_path));
+                        say!(ui, "{} File checksum (SHA-256): {}", style::bullet(), paint(Tone::Id, checksum));
+                        print_file_info(ui, &decrypted.info);
+                    }
+                    PayloadKind::Archive => {
+                        say!(ui, "{} Directory restored to: {}", style::bullet(), paint(Tone::Value, out_path));
+                        say!(ui, "{} Archive checksum (SHA-256): {}", style::bullet(), paint(Tone::Id, checksum));
+                    }
                 }
-
-                let mut decryption_key = generate_key();
-                loop {
-                    println!("\n\x1b[92mGenerated Decryption Key:\x1b[0m\n\x1b[96m{}\x1b[0m", decryption_key);
-                    println!("\nOptions:\n  [a] Accept key\n  [r] Regenerate\n  [q] Cancel\n");
-                    print!("\nType option (a, r, q) and press [Enter]: ");
-                    io::stdout().flush().unwrap();
-
-                    let mut kinput = String::new();
-                    io::stdin().read_line(&mut kinput).unwrap();
-                    match kinput.trim() {
-                        "a" => break,
-                        "r" => decryption_key = generate_key(),
-                        "q" => return,
-                        _ => println!("\x1b[91mInvalid input.\x1b[0m"),
+                match decrypted.integrity {
+                    Integrity::Verified => {
+                        say!(ui, "{} Integrity check: {} (matches the checksum sealed at encryption)\n", style::bullet(), paint(Tone::Success, "PASSED"));
+                    }
+                    Integrity::Unverified => {
+                        say!(ui, "{} Integrity check: {}", style::bullet(), paint(Tone::Warning, "UNAVAILABLE"));
+                        say!(ui, "\n{} This file uses the old format, which stores no checksum.", paint(Tone::Warning, "WARNING:"));
+                        say!(ui, "  Re-encrypt it to get automatic integrity checks.\n");
                     }
                 }
+                ui.record(format!("Decrypted {}", file_path.file_name().unwrap_or_default().to_string_lossy()), None);
 
-                let plaintext = fs::read(&file_path).expect("Failed to read file.");
-                let key_bytes = base64::decode(&decryption_key).expect("Invalid base64 key");
-                let encrypted = encrypt_aes256_cbc(&plaintext, &key_bytes)
-                    .expect("Encryption failed");
-
-                let out_path = format!("data/encrypted/{}.locked",
-                    Path::new(&file_path)
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                );
-                fs::create_dir_all("data/encrypted").ok();
-                fs::write(&out_path, &encrypted).expect("Failed to write encrypted file.");
-
-                let checksum = sha256_checksum(&encrypted);
-
-                println!("\n\n\x1b[92m✅ Encryption Complete\x1b[0m");
-                println!("→ Encrypted file saved to: \x1b[96m{}\x1b[0m", out_path);
-                println!("→ Integrity checksum: \x1b[95m{}\x1b[0m", checksum);
-                println!("\n\x1b[93mIMPORTANT:\x1b[0m Keep your decryption key and checksum hash safe.\n\n");
-
-                loop {
-                    println!("Options:\n  [m] Main menu\n  [q] Quit");
-                    let mut final_input = String::new();
-                    io::stdin().read_line(&mut final_input).unwrap();
-                    match final_input.trim() {
-                        "m" => return,
-                        "q" => std::process::exit(0),
-                        _ => println!("\x1b[91mInvalid input.\x1b[0m"),
+                return done(ui);
+            }
+            Some('l') => {
+                let Some((_, decrypted)) = open_selected_file(ui, config)? else {
+                    continue;
+                };
+
+                if decrypted.kind != PayloadKind::Archive {
+                    say!(ui, "\n{}", paint(Tone::Warning, format_args!("This is a single encrypted file ({} bytes), not a directory archive.", decrypted.plaintext.len())));
+                    continue;
+                }
+                match archive::list(&decrypted.plaintext) {
+                    Ok(entries) => {
+                        say!(ui, "\n{}\n", paint(Tone::Success, style::text("✅ Authenticated archive contents:")));
+                        for entry in &entries {
+                            say!(ui, "  {}", archive::format_entry(entry));
+                        }
+                        say!(ui, "\n  {} entries. Nothing was extracted.", entries.len());
                     }
+                    Err(e) => say!(ui, "{}", paint(Tone::Error, format_args!("Failed to read archive: {}", e))),
                 }
             }
-            "m" => return,
-            "q" => std::process::exit(0),
-            _ => println!("\x1b[91mInvalid choice.\x1b[0m"),
+            Some('s') => shred_decrypted(ui, &decrypted_dir)?,
+            Some('q') => return Ok(Next::Quit),
+            _ => return Ok(Next::Menu),
         }
     }
 }
 
-fn select_file(dir: &str) -> Option<String> {
-    let entries = fs::read_dir(dir).ok()?
-        .filter_map(Result::ok)
-        .filter(|e| e.path().is_file())
-        .collect::<Vec<_>>();
-
+// Overwrites and deletes leftover plaintext in the decrypted directory after confirmation
+fn shred_decrypted(ui: &mut Ui, dir: &Path) -> Result<(), Error> {
+    let entries = shred::entries(dir).unwrap_or_default();
     if entries.is_empty() {
-        println!("\x1b[91mNo files found in {}/\x1b[0m", dir);
-        return None;
+        say!(ui, "\n{}", paint(Tone::Warning, format_args!("Nothing to shred in {}/.", dir.display())));
+        return Ok(());
     }
 
-    println!("\nAvailable files:\n");
-    for (i, entry) in entries.iter().enumerate() {
-        println!("  [{}] {}", i + 1, entry.path().display());
+    say!(ui, "\nThese will be overwritten and deleted:");
+    for entry in &entries {
+        say!(ui, "  {}", entry.display());
+    }
+    say!(ui, "\n{} {}", paint(Tone::Warning, "NOTE:"), shred::CAVEAT);
+    if !ui.confirm("Continue?")? {
+        say!(ui, "{}", paint(Tone::Warning, "Cancelled, nothing was shredded."));
+        return Ok(());
     }
 
-    print!("\nSelect file by number: ");
-    io::stdout().flush().unwrap();
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).unwrap();
-
-    input.trim().parse::<usize>().ok()
-        .and_then(|i| entries.get(i - 1))
-        .map(|e| e.path().to_string_lossy().to_string())
+    let mut count = 0;
+    for entry in &entries {
+        match shred::shred_path(entry) {
+            Ok(n) => count += n,
+            Err(e) => say!(ui, "{}", paint(Tone::Error, format_args!("Failed to shred {}: {}", entry.display(), e))),
+        }
+    }
+    say!(ui, "\n{}", paint(Tone::Success, format_args!("{} Shredded {} file(s).", style::text("✅"), count)));
+    Ok(())
 }
 
-fn generate_key() -> String {
-    let mut rng = rand::thread_rng();
-    let mut key_bytes = [0u8; 32];
-    rng.fill(&mut key_bytes);
-    base64::encode(key_bytes)
+pub fn show_hmac_menu(ui: &mut Ui) {
+    ui.screen("🔏 HMAC");
+    say!(ui, "Computes a keyed HMAC tag for a message, e.g. to check a webhook signature.\n");
 }
 
-pub fn show_decrypt_menu() {
-    println!("\n\n\n  📥 Unpack Mode Selected");
-    println!("\n\n  WARNING:\n\n  Please ensure the target file to decrypt has been placed in /crypto-bro/data/encrypted directory.\n  Any duplicate namespace in data/decrypted will be overwritten.");
-    println!("\n\n  For files secured by this program only. If a file was encrypted with a different tool or parameters,\n  this decryptor likely won’t work.");
-    println!("\n  Max file size allowed: {:.2} MiB\n", max_allowed_file_size() as f64 / 1024.0 / 1024.0);
-    println!("\n\n  Be prepared to provide your recovery key on the next screen.");
-    println!("\n  Options:");
-    println!("    [d] Decrypt file");
-    println!("    [m] Main menu");
-    println!("    [q] Exit the app\n");
-    print!("\nType option (d, m, q) and press [Enter]: ");
+fn select_hmac_algorithm(ui: &mut Ui) -> Result<Option<HmacAlgorithm>, Error> {
+    let items = [Item::new('1', "HMAC-SHA256"), Item::new('2', "HMAC-SHA384"), Item::new('3', "HMAC-SHA512")];
+    match ui.choose("Algorithm:", &items, '1')? {
+        Some('1') => Ok(Some(HmacAlgorithm::Sha256)),
+        Some('2') => Ok(Some(HmacAlgorithm::Sha384)),
+        Some('3') => Ok(Some(HmacAlgorithm::Sha512)),
+        _ => Ok(None),
+    }
 }
 
-pub fn decrypt_menu_loop() {
+pub fn hmac_menu_loop(ui: &mut Ui, config: &Config) -> Result<Next, Error> {
+    show_hmac_menu(ui);
+    let items = [
+        Item::new('t', "HMAC of typed text"),
+        Item::new('f', "HMAC of a file"),
+        Item::new('m', "Main menu"),
+        Item::new('q', "Exit the app"),
+    ];
     loop {
-        show_decrypt_menu();
-        io::stdout().flush().unwrap();
-
-        let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        let choice = input.trim();
-
-        match choice {
-            "d" => {
-                let file_path = match select_file("data/encrypted") {
-                    Some(f) => f,
-                    None => {
-                        println!("\x1b[91mNo valid file selected.\x1b[0m");
+        let message = match ui.choose("", &items, 't')? {
+            Some('t') => {
+                let Some(text) = ui.input("Enter the message:")? else {
+                    continue;
+                };
+                text.into_bytes()
+            }
+            Some('f') => {
+                let Some(file_path) = ui.browse("File:", &config.paths.data_dir, Pick::File, &[], |_| true)? else {
+                    say!(ui, "{}", paint(Tone::Error, "No valid file selected."));
+                    continue;
+                };
+                match fs::read(&file_path) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        say!(ui, "{}", paint(Tone::Error, format_args!("Failed to read file: {}", e)));
                         continue;
                     }
-                };
+                }
+            }
+            Some('q') => return Ok(Next::Quit),
+            _ => return Ok(Next::Menu),
+        };
+
+        let Some(algorithm) = select_hmac_algorithm(ui)? else {
+            say!(ui, "{}", paint(Tone::Error, "Invalid algorithm."));
+            continue;
+        };
+
+        let key = match ui.input_hidden("Enter the HMAC key:")? {
+            Some(k) if !k.is_empty() => k,
+            _ => {
+                say!(ui, "{}", paint(Tone::Error, "No key entered."));
+                continue;
+            }
+        };
+
+        let tag = mac::compute_hmac(algorithm, key.as_bytes(), message.as_slice())?;
+        say!(ui, "\n{}", paint(Tone::Success, format_args!("{} {} Computed", style::text("✅"), algorithm.name())));
+        say!(ui, "{} Hex:    {}", style::bullet(), paint(Tone::Value, hex::encode(&tag)));
+        say!(ui, "{} Base64: {}\n", style::bullet(), paint(Tone::Value, base64::encode(&tag)));
+        ui.record(algorithm.name(), Some(hex::encode(&tag)));
+
+        let expected = ui.input("Paste an expected tag to verify, or press [Enter] to skip:")?.unwrap_or_default();
+        if !expected.trim().is_empty() {
+            match mac::decode_tag(algorithm, &expected) {
+                Ok(bytes) if mac::verify_hmac(algorithm, key.as_bytes(), message.as_slice(), &bytes)? => {
+                    say!(ui, "{}", paint(Tone::Success, style::text("✅ Tag matches.")));
+                }
+                Ok(_) => say!(ui, "{}", paint(Tone::Error, style::text("❌ Tag does not match."))),
+                Err(e) => say!(ui, "{}", paint(Tone::Error, e)),
+            }
+        }
 
-                let metadata = fs::metadata(&file_path).expect("Could not read file metadata");
-                let file_size = metadata.len();
-                let max_size = max_allowed_file_size();
+        return done(ui);
+    }
+}
 
-                if file_size > max_size {
-                    println!("\x1b[91mFile too large. Limit is {:.2} MiB\x1b[0m", max_size as f64 / 1024.0 / 1024.0);
-                    continue;
-                }
+pub fn show_shares_menu(ui: &mut Ui) {
+    ui.screen("🧩 Secret sharing");
+    say!(ui, "Splits a key or secret into N shares, any K of which recover it (Shamir's secret sharing).");
+    say!(ui, "Fewer than K shares reveal nothing about the secret.\n");
+}
 
-                print!("Enter your Base64 encoded decryption key: ");
-                io::stdout().flush().unwrap();
-                let mut key_input = String::new();
-                io::stdin().read_line(&mut key_input).unwrap();
-                let key_input = key_input.trim();
+fn read_count(ui: &mut Ui, prompt: &str) -> Result<Option<u8>, Error> {
+    Ok(ui.input(prompt)?.and_then(|input| input.trim().parse::<u8>().ok()))
+}
 
-                let key_bytes = match base64::decode(key_input) {
-                    Ok(k) if k.len() == 32 => k,
-                    _ => {
-                        println!("\x1b[91mInvalid key format or length.\x1b[0m");
-                        continue;
-                    }
-                };
+fn select_share_encoding(ui: &mut Ui) -> Result<Option<ShareEncoding>, Error> {
+    let items = [
+        Item::new('1', "Text").detail("Base64"),
+        Item::new('2', "Hex"),
+        Item::new('3', "Words").detail("easiest to write down"),
+    ];
+    match ui.choose("Share format:", &items, '1')? {
+        Some('1') => Ok(Some(ShareEncoding::Text)),
+        Some('2') => Ok(Some(ShareEncoding::Hex)),
+        Some('3') => Ok(Some(ShareEncoding::Mnemonic)),
+        _ => Ok(None),
+    }
+}
 
-                let ciphertext = match fs::read(&file_path) {
-                    Ok(c) => c,
-                    Err(_) => {
-                        println!("\x1b[91mFailed to read encrypted file.\x1b[0m");
-                        continue;
-                    }
-                };
+// Asks how to split a secret and shows the shares
+fn split_secret(ui: &mut Ui, kind: SecretKind, secret: &[u8]) -> Result<(), Error> {
+    let Some(shares) = read_count(ui, "How many shares should be created? (2-255):")? else {
+        say!(ui, "{}", paint(Tone::Error, "Invalid number."));
+        return Ok(());
+    };
+    let Some(threshold) = read_count(ui, "How many shares should be needed to recover it?:")? else {
+        say!(ui, "{}", paint(Tone::Error, "Invalid number."));
+        return Ok(());
+    };
+    let Some(encoding) = select_share_encoding(ui)? else {
+        say!(ui, "{}", paint(Tone::Error, "Invalid format."));
+        return Ok(());
+    };
+
+    match shamir::split(secret, kind, threshold, shares) {
+        Ok(split) => {
+            say!(ui, "\n{}\n", paint(Tone::Success, format_args!("{} Created {} shares, any {} recover the secret", style::text("✅"), shares, threshold)));
+            for share in &split {
+                say!(ui, "Share {} of {}:\n{}\n", share.index, shares, paint(Tone::Value, share.encode(encoding)));
+            }
+            say!(ui, "{} Give each share to a different person. Keep no more than {} together.\n", paint(Tone::Warning, "IMPORTANT:"), threshold - 1);
+            ui.record(format!("Split into {} shares", shares), None);
+        }
+        Err(e) => say!(ui, "{}", paint(Tone::Error, e)),
+    }
+    Ok(())
+}
 
-                let decrypted: Vec<u8> = match decrypt_aes256_cbc(&ciphertext, &key_bytes) {
-                    Ok(vec) => vec, 
-                    Err(e) => {
-                        println!("\x1b[91mDecryption failed: {}\x1b[0m", e);
-                        continue;
-                    }
-                };
+// Reads shares one at a time, without echoing them, until enough have been entered
+fn combine_shares(ui: &mut Ui) -> Result<(), Error> {
+    let mut shares: Vec<Share> = Vec::new();
+    loop {
+        let needed = shares.first().map(|s| s.threshold as usize);
+        if needed.is_some_and(|n| shares.len() >= n) {
+            break;
+        }
+        let prompt = match needed {
+            Some(n) => format!("Share {} of {} (empty to cancel):", shares.len() + 1, n),
+            None => "Share 1 (empty to cancel):".to_string(),
+        };
+        match ui.input_hidden(&prompt)? {
+            Some(line) if !line.trim().is_empty() => match Share::decode(&line) {
+                Ok(share) => shares.push(share),
+                Err(e) => say!(ui, "{}", paint(Tone::Error, e)),
+            },
+            _ => return Ok(()),
+        }
+    }
 
-                let out_path = format!("data/decrypted/{}",
-                    Path::new(&file_path)
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                        .replace(".locked", "")
-                );
-
-                fs::create_dir_all("data/decrypted").ok();
-                if let Err(e) = fs::write(&out_path, &decrypted) {
-                    println!("\x1b[91mFailed to write decrypted file: {}\x1b[0m", e);
-                    continue;
-                }
+    match shamir::combine(&shares) {
+        Ok((SecretKind::Key, key)) => {
+            say!(ui, "\n{}", paint(Tone::Success, style::text("✅ Key recovered")));
+            say!(ui, "{} Key: {}", style::bullet(), paint(Tone::Value, base64::encode(&key)));
+            say!(ui, "{} As words: {}", style::bullet(), paint(Tone::Value, mnemonic::encode_key(&key)?));
+            say!(ui, "{} Key ID: {}\n", style::bullet(), paint(Tone::Id, container::key_id_hex(&container::key_id(&key))));
+            ui.record("Recovered key", Some(base64::encode(&key)));
+        }
+        Ok((SecretKind::Text, secret)) => {
+            say!(ui, "\n{}", paint(Tone::Success, style::text("✅ Secret recovered")));
+            say!(ui, "{} {}\n", style::bullet(), paint(Tone::Value, String::from_utf8_lossy(&secret)));
+            ui.record("Recovered secret", Some(String::from_utf8_lossy(&secret).to_string()));
+        }
+        Err(e) => say!(ui, "{}", paint(Tone::Error, format_args!("{} {}", style::text("❌"), e))),
+    }
+    Ok(())
+}
 
-                let checksum = sha256_checksum(&decrypted);
-
-                println!("\n\n\x1b[92m✅ Decryption Complete\x1b[0m");
-                println!("→ Decrypted file saved to: \x1b[96m{}\x1b[0m", out_path);
-                println!("→ Integrity checksum: \x1b[95m{}\x1b[0m", checksum);
-                println!("\n\x1b[93mIMPORTANT:\x1b[0m Verify checksum matches expected value.\n");
-
-                loop {
-                    println!("Options:\n  [m] Main menu\n  [q] Quit");
-                    let mut final_input = String::new();
-                    io::stdin().read_line(&mut final_input).unwrap();
-                    match final_input.trim() {
-                        "m" => return,
-                        "q" => std::process::exit(0),
-                        _ => println!("\x1b[91mInvalid input.\x1b[0m"),
+pub fn shares_menu_loop(ui: &mut Ui) -> Result<Next, Error> {
+    show_shares_menu(ui);
+    let items = [
+        Item::new('s', "Split a key or secret"),
+        Item::new('c', "Combine shares"),
+        Item::new('m', "Main menu"),
+        Item::new('q', "Exit the app"),
+    ];
+    loop {
+        match ui.choose("", &items, 's')? {
+            Some('s') => {
+                let secret = match ui.input_hidden("Enter the key or secret to split:")? {
+                    Some(s) if !s.is_empty() => s,
+                    _ => {
+                        say!(ui, "{}", paint(Tone::Error, "Nothing entered."));
+                        continue;
                     }
+                };
+                let (kind, secret) = shamir::classify(&secret);
+                if kind == SecretKind::Key {
+                    say!(ui, "{} Recognised a 256-bit key (key ID {})", style::bullet(), paint(Tone::Id, container::key_id_hex(&container::key_id(&secret))));
                 }
+                split_secret(ui, kind, &secret)?;
             }
-            "m" => return,
-            "q" => std::process::exit(0),
-            _ => println!("\x1b[91mInvalid choice.\x1b[0m"),
+            Some('c') => combine_shares(ui)?,
+            Some('q') => return Ok(Next::Quit),
+            _ => return Ok(Next::Menu),
         }
     }
-}
\ No newline at end of file
+}
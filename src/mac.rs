@@ -0,0 +1,161 @@
+// mac.rs
+
+use clap::ValueEnum;
+use hmac::{Hmac, Mac};
+use sha2::{Sha256, Sha384, Sha512};
+use std::io::{self, Read};
+
+use crate::error::{Error, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum HmacAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    Hex,
+    Base64,
+}
+
+impl HmacAlgorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            HmacAlgorithm::Sha256 => "HMAC-SHA256",
+            HmacAlgorithm::Sha384 => "HMAC-SHA384",
+            HmacAlgorithm::Sha512 => "HMAC-SHA512",
+        }
+    }
+
+    /// What webhook signature headers put before a tag, e.g. `sha256=`
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            HmacAlgorithm::Sha256 => "sha256=",
+            HmacAlgorithm::Sha384 => "sha384=",
+            HmacAlgorithm::Sha512 => "sha512=",
+        }
+    }
+
+    /// Length of the tag in bytes
+    pub fn output_len(&self) -> usize {
+        match self {
+            HmacAlgorithm::Sha256 => 32,
+            HmacAlgorithm::Sha384 => 48,
+            HmacAlgorithm::Sha512 => 64,
+        }
+    }
+}
+
+impl Encoding {
+    pub fn encode(&self, bytes: &[u8]) -> String {
+        match self {
+            Encoding::Hex => hex::encode(bytes),
+            Encoding::Base64 => base64::encode(bytes),
+        }
+    }
+}
+
+// Feeds a reader into the MAC in chunks so large inputs are never fully buffered
+fn update_from<M: Mac>(mut mac: M, mut reader: impl Read) -> io::Result<M> {
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            return Ok(mac);
+        }
+        mac.update(&buf[..n]);
+    }
+}
+
+/// Computes the HMAC of everything read from `reader`
+pub fn compute_hmac(algorithm: HmacAlgorithm, key: &[u8], reader: impl Read) -> io::Result<Vec<u8>> {
+    // HMAC accepts keys of any length, so new_from_slice cannot fail
+    Ok(match algorithm {
+        HmacAlgorithm::Sha256 => update_from(Hmac::<Sha256>::new_from_slice(key).unwrap(), reader)?
+            .finalize().into_bytes().to_vec(),
+        HmacAlgorithm::Sha384 => update_from(Hmac::<Sha384>::new_from_slice(key).unwrap(), reader)?
+            .finalize().into_bytes().to_vec(),
+        HmacAlgorithm::Sha512 => update_from(Hmac::<Sha512>::new_from_slice(key).unwrap(), reader)?
+            .finalize().into_bytes().to_vec(),
+    })
+}
+
+/// Checks `reader` against an expected tag in constant time
+pub fn verify_hmac(algorithm: HmacAlgorithm, key: &[u8], reader: impl Read, expected: &[u8]) -> io::Result<bool> {
+    Ok(match algorithm {
+        HmacAlgorithm::Sha256 => update_from(Hmac::<Sha256>::new_from_slice(key).unwrap(), reader)?
+            .verify_slice(expected).is_ok(),
+        HmacAlgorithm::Sha384 => update_from(Hmac::<Sha384>::new_from_slice(key).unwrap(), reader)?
+            .verify_slice(expected).is_ok(),
+        HmacAlgorithm::Sha512 => update_from(Hmac::<Sha512>::new_from_slice(key).unwrap(), reader)?
+            .verify_slice(expected).is_ok(),
+    })
+}
+
+/// Decodes an expected tag given as hex or Base64, with an optional `sha256=` style prefix
+/// as sent in webhook signature headers. A prefix naming another algorithm is an error.
+pub fn decode_tag(algorithm: HmacAlgorithm, tag: &str) -> Result<Vec<u8>> {
+    let mut tag = tag.trim();
+    for other in HmacAlgorithm::value_variants() {
+        if let Some(rest) = tag.strip_prefix(other.prefix()) {
+            if *other != algorithm {
+                return Err(Error::InvalidInput(format!(
+                    "The tag starts with `{}`, so it is {}, not {}.",
+                    other.prefix(),
+                    other.name(),
+                    algorithm.name()
+                )));
+            }
+            tag = rest;
+        }
+    }
+
+    let len = algorithm.output_len();
+    if tag.len() == len * 2 && let Ok(bytes) = hex::decode(tag) {
+        return Ok(bytes);
+    }
+    match base64::decode(tag) {
+        Ok(bytes) if bytes.len() == len => Ok(bytes),
+        _ => Err(Error::InvalidInput(format!("Expected a {}-byte {} tag in hex or Base64.", len, algorithm.name()))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4231, test case 2
+    const KEY: &[u8] = b"Jefe";
+    const DATA: &[u8] = b"what do ya want for nothing?";
+    const SHA256_TAG: &str = "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843";
+
+    #[test]
+    fn matches_rfc_4231() {
+        let tag = compute_hmac(HmacAlgorithm::Sha256, KEY, DATA).unwrap();
+        assert_eq!(hex::encode(&tag), SHA256_TAG);
+        let tag = compute_hmac(HmacAlgorithm::Sha512, KEY, DATA).unwrap();
+        assert_eq!(
+            hex::encode(&tag),
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737"
+        );
+        assert!(verify_hmac(HmacAlgorithm::Sha256, KEY, DATA, &tag[..32]).is_ok_and(|valid| !valid));
+    }
+
+    #[test]
+    fn decodes_prefixed_tags() {
+        let expected = hex::decode(SHA256_TAG).unwrap();
+        assert_eq!(decode_tag(HmacAlgorithm::Sha256, SHA256_TAG).unwrap(), expected);
+        assert_eq!(decode_tag(HmacAlgorithm::Sha256, &format!("sha256={}", SHA256_TAG)).unwrap(), expected);
+        assert_eq!(decode_tag(HmacAlgorithm::Sha256, &base64::encode(&expected)).unwrap(), expected);
+        assert!(verify_hmac(HmacAlgorithm::Sha256, KEY, DATA, &expected).unwrap());
+    }
+
+    #[test]
+    fn rejects_a_prefix_for_another_algorithm() {
+        let error = decode_tag(HmacAlgorithm::Sha256, &format!("sha512={}", SHA256_TAG)).unwrap_err();
+        assert!(matches!(error, Error::InvalidInput(_)));
+        assert!(error.to_string().contains("HMAC-SHA512, not HMAC-SHA256"));
+    }
+}
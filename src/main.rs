@@ -1,162 +1,521 @@
 // main.rs
 
-mod crypto_control;
+mod style;
 mod lunchbox;
-mod ascii;
+mod banner;
+mod prompt;
+mod tui;
+mod cli;
+// The library's test fixtures, not all of which the app's tests use
+#[cfg(test)]
+#[path = "testing.rs"]
+#[allow(dead_code)]
+mod testing;
 
-use crossterm::{
-    event::{self, KeyCode, KeyEvent},
-    terminal::{disable_raw_mode, enable_raw_mode},
-};
-use std::io::{self, Write};
+use key_gen_app::config::{ColorChoice, Config};
+use key_gen_app::generator::{self, Defaults, Generated, Kind, Preset, Setting};
+use key_gen_app::{clipboard, mnemonic, Error};
+
+use banner::Banner;
+use style::{paint, paint_err, Tone};
+use tui::{say, Field, Item, Next, Ui, Value};
+
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
 use clap::Parser;
 
 #[derive(Parser)]
 #[command(name = "Key Generator", version = "0.1.0", about = "The Rust-based cryptographic multi-tool.")]
 struct Args {
-    #[arg(short = 'k', help = "Key type: 1 (OpenSSL), 2 (Ring), 3 (UUID), 4 (API Key), 5 (16char Password), 6 (32char Password), 7 (Username)")]
+    #[arg(short = 'k', help = key_help())]
     key: Option<u32>,
+
+    #[arg(short = 'n', long, value_name = "N", requires = "key", help = "Print N keys of the -k type and exit, one per line")]
+    count: Option<usize>,
+
+    #[arg(long, requires = "key", help = "Print the -k keys as JSON, with their entropy, and exit")]
+    json: bool,
+
+    #[arg(long, value_name = "SECS", help = "Clear a copied key from the clipboard after this many seconds (0 never clears it, default 30)")]
+    clipboard_timeout: Option<u64>,
+
+    #[arg(long, value_name = "NAME", help = "Banner for the main menu: a name from `banners`, random or none (default: random)")]
+    banner: Option<String>,
+
+    #[arg(long, conflicts_with = "banner", help = "Don't show a banner in the main menu")]
+    no_banner: bool,
+
+    #[arg(long, help = "Ask clipboard managers not to keep copied keys in their history")]
+    no_clipboard_history: bool,
+
+    #[arg(long, value_enum, help = "Clipboard to copy keys to (default: auto)")]
+    clipboard: Option<clipboard::BackendChoice>,
+
+    #[arg(long, global = true, value_name = "FILE", help = "Config file (default: ~/.config/crypto-bro/config.toml, or $CRYPTO_BRO_CONFIG)")]
+    config: Option<PathBuf>,
+
+    #[arg(long, global = true, value_enum, value_name = "WHEN", help = "Colour the output: auto (on a terminal, unless NO_COLOR is set), always or never")]
+    color: Option<ColorChoice>,
+
+    #[arg(long, global = true, help = "Write words instead of emoji")]
+    no_emoji: bool,
+
+    #[arg(long, global = true, help = "Line-by-line menus and plain text, for screen readers and logs")]
+    plain: bool,
+
+    #[command(subcommand)]
+    command: Option<cli::Command>,
 }
 
-fn generate_key(choice: u32) -> Option<(String, &'static str, u32)> {
-    match choice {
-        1 => Some((crypto_control::generate_openssl_key(), "Base64 encoded, 256-bit (OpenSSL)", 1)),
-        2 => Some((crypto_control::generate_ring_key(), "Base64 encoded, 256-bit (Ring)", 2)),
-        3 => Some((crypto_control::generate_uuid(), "UUID (v4)", 3)),
-        4 => Some((crypto_control::generate_api_key(), "Base64 encoded, 256-bit (API Key)", 4)),
-        5 => Some((crypto_control::generate_password(16), "16-character password", 5)),
-        6 => Some((crypto_control::generate_password(32), "32-character password", 6)),
-        7 => Some((crypto_control::generate_username(), "Username (Word list generated)", 7)),
-        _ => None,
+// One of the main menu's own entries, with what [h] says about it
+struct MenuEntry {
+    key: char,
+    label: &'static str,
+    detail: Option<&'static str>,
+    help: fn(&Config) -> String,
+    open: fn(&mut Ui, &Config) -> Result<Next, Error>,
+}
+
+// The main menu's own entries. The generators' entries take the other keys, in order.
+const MENU: [MenuEntry; 6] = [
+    MenuEntry {
+        key: '8',
+        label: "Encrypt file",
+        detail: Some("AES 256-bit"),
+        help: |config| format!("Encrypts a file, or a directory as one archive, with a new 256-bit key. The browser starts in {}/. Keep the key safe!", config.paths.data_dir.display()),
+        open: lunchbox::encrypt_menu_loop,
+    },
+    MenuEntry {
+        key: '9',
+        label: "Decrypt file",
+        detail: Some("AES 256-bit"),
+        help: |config| format!("Decrypts a file with its key or key words, lists archives and shreds leftovers. The browser starts in {}/.", config.paths.encrypted_dir().display()),
+        open: lunchbox::decrypt_menu_loop,
+    },
+    MenuEntry {
+        key: 'a',
+        label: "HMAC",
+        detail: Some("SHA-256/384/512"),
+        help: |_| "Computes or verifies an HMAC tag for a file or text, e.g. a webhook signature.".into(),
+        open: lunchbox::hmac_menu_loop,
+    },
+    MenuEntry {
+        key: 's',
+        label: "Secret sharing",
+        detail: Some("Shamir split / combine"),
+        help: |_| "Splits a key or secret into shares, any K of which recover it, or combines them.".into(),
+        open: |ui, _| lunchbox::shares_menu_loop(ui),
+    },
+    MenuEntry {
+        key: 'h',
+        label: "Help",
+        detail: None,
+        help: |_| "Shows what each option does and how to move around.".into(),
+        open: show_help,
+    },
+    MenuEntry {
+        key: 'q',
+        label: "Quit",
+        detail: None,
+        help: |_| "Clears a key still on the clipboard and exits.".into(),
+        open: |_, _| Ok(Next::Quit),
+    },
+];
+
+fn preset_keys() -> impl Iterator<Item = char> {
+    ('1'..='9').chain('a'..='z').filter(|key| MENU.iter().all(|entry| entry.key != *key))
+}
+
+// -k help, listing the numbered menu entries
+fn key_help() -> String {
+    let types = generator::presets(&Defaults::BUILT_IN).iter().map(|p| format!("{} = {}", p.number, p.label())).collect::<Vec<_>>();
+    format!("Key type, as in the main menu (`gen` does the same by name): {}", types.join(", "))
+}
+
+// Generates a key and shows it, with its words for cryptographic keys
+fn show_generated(ui: &mut Ui, preset: &Preset) -> Option<Generated> {
+    match preset.generate() {
+        Ok(generated) => {
+            say!(ui, "{} {}", paint(Tone::Success, "Generated Key:"), paint(Tone::Preset, format_args!("({})", preset.label())));
+            say!(ui, "{}", paint(Tone::Value, &generated.value));
+            print_key_words(ui, &generated);
+            ui.record(preset.label(), Some(generated.value.clone()));
+            Some(generated)
+        }
+        Err(e) => {
+            say!(ui, "{}", paint(Tone::Error, e));
+            None
+        }
+    }
+}
+
+// Cryptographic keys can also be written down as BIP39 words
+fn print_key_words(ui: &mut Ui, generated: &Generated) {
+    if generated.generator.is_key()
+        && let Ok(bytes) = base64::decode(&generated.value)
+        && let Ok(words) = mnemonic::encode_key(&bytes)
+    {
+        say!(ui, "{} {}", paint(Tone::Success, "As words:"), paint(Tone::Value, words));
     }
 }
 
-fn get_keypress() -> Option<KeyEvent> {
-    enable_raw_mode().unwrap();
-    let result = loop {
-        if event::poll(std::time::Duration::from_millis(500)).unwrap() {
-            if let event::Event::Key(key) = event::read().unwrap() {
-                break Some(key);
+// A form for the generator's parameters; false if it was cancelled
+fn edit_options(ui: &mut Ui, preset: &mut Preset) -> Result<bool, Error> {
+    let parameters = preset.generator.parameters();
+    let mut fields = parameters.iter().map(|parameter| {
+        let value = match (parameter.kind, parameter.value(&preset.options)) {
+            (Kind::Number { min, max, .. }, Setting::Number(value)) => Value::Number { value, min, max },
+            (Kind::Choice { choices, .. }, Setting::Choice(choice)) => {
+                Value::Choice { options: choices, index: choices.iter().position(|c| *c == choice).unwrap_or(0) }
             }
+            (_, setting) => Value::Toggle(setting == Setting::Toggle(true)),
+        };
+        Field { label: parameter.name, help: parameter.description, value }
+    }).collect::<Vec<_>>();
+
+    if !ui.form(&format!("{} options:", preset.generator.title()), &mut fields)? {
+        return Ok(false);
+    }
+    for (parameter, field) in parameters.iter().zip(&fields) {
+        let value = match field.value {
+            Value::Number { value, .. } => Setting::Number(value),
+            Value::Toggle(on) => Setting::Toggle(on),
+            Value::Choice { options, index } => Setting::Choice(options[index]),
+        };
+        parameter.set(&mut preset.options, value);
+    }
+    Ok(true)
+}
+
+// Generates a key and offers what can be done with it
+fn key_screen(ui: &mut Ui, config: &Config, mut preset: Preset) -> Result<Next, Error> {
+    ui.screen(preset.generator.title());
+    let mut generated = show_generated(ui, &preset);
+
+    let mut items = vec![
+        Item::new('c', "Copy to clipboard"),
+        Item::new('p', "Paper backup"),
+        Item::new('r', "Regenerate"),
+    ];
+    if !preset.generator.parameters().is_empty() {
+        items.push(Item::new('o', "Options").detail("change them and regenerate"));
+    }
+    items.push(Item::new('m', "Main menu"));
+    items.push(Item::new('q', "Exit the app"));
+
+    let mut selected = 'c';
+    loop {
+        let Some(choice) = ui.choose("", &items, selected)? else {
+            return Ok(Next::Menu);
+        };
+        selected = choice;
+        match choice {
+            'c' | 'p' if generated.is_none() => say!(ui, "{}", paint(Tone::Error, "Nothing was generated, press 'r' to try again.")),
+            'c' => {
+                if let Some(generated) = &generated {
+                    ui.copy(&generated.value);
+                }
+            }
+            'p' => {
+                if let Some(generated) = &generated {
+                    let created = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+                    let value = generated.value.clone();
+                    lunchbox::save_paper_backup(ui, config, &value, None, &format!("key-{}", created.as_secs()))?;
+                }
+            }
+            'r' => {
+                say!(ui);
+                generated = show_generated(ui, &preset);
+            }
+            'o' => {
+                if edit_options(ui, &mut preset)? {
+                    say!(ui);
+                    generated = show_generated(ui, &preset);
+                }
+            }
+            'q' => return Ok(Next::Quit),
+            _ => return Ok(Next::Menu),
         }
-    };
-    disable_raw_mode().unwrap();
-    result
+    }
+}
+
+// The `gen` command line that generates the same as a menu entry
+fn gen_command(preset: &Preset) -> String {
+    let mut command = format!("gen {}", preset.generator.name());
+    for parameter in preset.generator.parameters() {
+        let flag = match (parameter.kind, parameter.value(&preset.options)) {
+            (Kind::Toggle { default, .. }, Setting::Toggle(on)) if on == default => continue,
+            (Kind::Toggle { .. }, Setting::Toggle(true)) => format!(" --{}", parameter.name),
+            (Kind::Toggle { off_flag, .. }, _) => format!(" --{}", off_flag),
+            (_, Setting::Number(value)) => format!(" --{} {}", parameter.name, value),
+            (_, Setting::Choice(choice)) => format!(" --{} {}", parameter.name, choice),
+            (_, Setting::Toggle(_)) => continue,
+        };
+        command.push_str(&flag);
+    }
+    command
+}
+
+// What each main menu option does, shown with [h]
+fn show_help(ui: &mut Ui, config: &Config) -> Result<Next, Error> {
+    ui.screen("Help");
+    for (p, key) in generator::presets(&config.defaults()).iter().zip(preset_keys()) {
+        say!(ui, "[{}] {}", key, p.label());
+        say!(ui, "    {}", paint(Tone::Muted, format_args!("{:.0} bits of entropy. From the command line: `{}`{}", p.generator.entropy(&p.options), gen_command(p), if p.generator.is_key() { ", can also be shown as words" } else { "" })));
+    }
+    for entry in &MENU {
+        say!(ui, "[{}] {}", entry.key, entry.label);
+        say!(ui, "    {}", paint(Tone::Muted, (entry.help)(config)));
+    }
+    say!(ui);
+    if ui.is_plain() {
+        say!(ui, "Type an option's key and press [Enter]. [Enter] alone takes the option named in the question, esc goes back.");
+        say!(ui, "After generating, c copies the key, p saves a paper backup, r regenerates it and o changes its options.");
+        say!(ui, "The file browser numbers its entries: type a number, or a path (~ is your home directory).");
+    } else {
+        say!(ui, "Move with the arrow keys and press [Enter], or press an option's key. [Esc] goes back.");
+        say!(ui, "After generating, [c] copies the key, [p] saves a paper backup, [r] regenerates it and [o] changes its options.");
+        say!(ui, "[Tab] moves to the history pane, where [Enter] copies a key again. [PgUp]/[PgDn] scroll, [Ctrl-C] quits.");
+        say!(ui, "In the file browser, [←] goes up a directory, [~] to your home directory and [.] shows hidden files.");
+        say!(ui, "[/] types a path ([Tab] completes it), [r] lists subdirectories too and [f] filters by extension.");
+    }
+    say!(ui, "Run with --help, or see the `man` command, for the command line options.\n");
+
+    match ui.choose("", &[Item::new('m', "Main menu"), Item::new('q', "Exit the app")], 'm')? {
+        Some('q') => Ok(Next::Quit),
+        _ => Ok(Next::Menu),
+    }
+}
+
+// The clear timer dies with the app, so a key still on the clipboard is cleared on the way out
+fn say_goodbye() {
+    if clipboard::clear_now() {
+        println!("\n{}", paint(Tone::Warning, "Clipboard cleared."));
+    }
+    println!("\n{}\n", paint(Tone::Success, "Goodbye!"));
 }
 
 fn main() {
-    let images = ascii::AsciiImages {
-        image1: "src/ascii/fsoc.txt",
-        image2: "src/ascii/crsbrks.txt",
+    let mut args = Args::parse();
+
+    if let Err(e) = run(&mut args) {
+        tui::restore();
+        eprintln!("{}", paint_err(Tone::Error, &e));
+        std::process::exit(e.exit_code());
+    }
+}
+
+fn run(args: &mut Args) -> Result<(), Error> {
+    // Until the config is read, so its errors look right too
+    style::setup(args.color.unwrap_or(if args.plain { ColorChoice::Never } else { ColorChoice::Auto }), !args.no_emoji && !args.plain);
+
+    // Works even when the current config file doesn't load, so a broken one can be replaced
+    if let Some(cli::Command::Config { action: cli::ConfigCommand::Init { force } }) = args.command {
+        return cli::init_config(args.config.as_deref(), force);
+    }
+    let config = Config::load(args.config.as_deref())?;
+    let (color, emoji) = display_settings(args, &config);
+    style::setup(color, emoji);
+
+    match (args.command.take(), args.key) {
+        (Some(command), _) => cli::run(command, &config),
+        (None, Some(number)) if args.count.is_some() || args.json => {
+            let preset = generator::preset(number, &config.defaults())?;
+            cli::print_generated(preset.generator, &preset.options, args.count.unwrap_or(1), args.json)
+        }
+        (None, _) => run_menu(args, &config),
+    }
+}
+
+// Colour and emoji from the flags, else the config file and variables
+fn display_settings(args: &Args, config: &Config) -> (ColorChoice, bool) {
+    let plain = args.plain || config.display.plain;
+    // Plain text has no colour unless it's asked for outright
+    let color = match (args.color, config.display.color) {
+        (Some(color), _) => color,
+        (None, ColorChoice::Auto) if plain => ColorChoice::Never,
+        (None, color) => color,
     };
+    (color, config.display.emoji && !args.no_emoji && !plain)
+}
+
+// Clipboard handling from the flags, else the config file and variables
+fn clipboard_settings(args: &Args, config: &Config) -> clipboard::Settings {
+    let timeout = args.clipboard_timeout.unwrap_or(config.clipboard.timeout);
+    clipboard::Settings {
+        backend: args.clipboard.unwrap_or(config.clipboard.backend),
+        clear_after: (timeout > 0).then(|| std::time::Duration::from_secs(timeout)),
+        exclude_from_history: args.no_clipboard_history || config.clipboard.no_history,
+    }
+}
+
+fn run_menu(args: &Args, config: &Config) -> Result<(), Error> {
+    // Without a terminal to draw on, the menus go line by line too
+    let plain = args.plain
+        || config.display.plain
+        || style::dumb_terminal()
+        || !io::stdin().is_terminal()
+        || !io::stdout().is_terminal();
+
+    let choice = if args.no_banner { "none" } else { args.banner.as_deref().unwrap_or(&config.display.banner) };
+    let (banner, skipped) = match banner::pick(choice, config.banner_dir().as_deref()) {
+        Ok((banner, skipped)) => (Ok(banner), skipped),
+        // A name given on the command line has to be right, a bad setting only costs the banner
+        Err(e) if args.banner.is_some() => return Err(Error::InvalidInput(format!("{}.", e))),
+        Err(e) => (Err(e), Vec::new()),
+    };
+
+    let mut ui = Ui::start(clipboard_settings(args, config), plain)?;
+    let result = main_menu(&mut ui, args, config, banner, &skipped);
+    let interrupted = ui.interrupted();
+    drop(ui);
+
+    match result {
+        _ if interrupted => {
+            clipboard::clear_now();
+            Err(Error::Cancelled("Interrupted.".into()))
+        }
+        Ok(()) => {
+            say_goodbye();
+            Ok(())
+        }
+        Err(e) => {
+            clipboard::clear_now();
+            Err(e)
+        }
+    }
+}
 
-    if let Err(e) = images.print_random_ascii() {
-        eprintln!("\x1b[91mFailed to load ASCII image: {}\x1b[0m", e);
+fn main_menu(ui: &mut Ui, args: &Args, config: &Config, banner: io::Result<Option<Banner>>, skipped: &[String]) -> Result<(), Error> {
+    let defaults = config.defaults();
+    let presets = generator::presets(&defaults);
+    if let Some(number) = args.key
+        && key_screen(ui, config, generator::preset(number, &defaults)?)? == Next::Quit
+    {
+        return Ok(());
     }
-    println!("\n    \x1b[92mHello, friend.\x1b[0m\n");
 
-    let args = Args::parse();
-    let mut preselected_key = args.key.and_then(generate_key);
+    let keyed = presets.iter().zip(preset_keys()).collect::<Vec<_>>();
+    let mut items = keyed
+        .iter()
+        .map(|(p, key)| Item::new(*key, p.generator.title()).detail(p.generator.description(&p.options)))
+        .collect::<Vec<_>>();
+    items.extend(MENU.iter().map(|entry| match entry.detail {
+        Some(detail) => Item::new(entry.key, entry.label).detail(detail),
+        None => Item::new(entry.key, entry.label),
+    }));
 
+    let mut selected = '1';
     loop {
-        let (key, key_type, key_choice) = if let Some(k) = preselected_key.take() {
-            k
-        } else {
-
-            println!("________________________________\n");
-            println!("    Main Menu:\n");
-            println!("    [1] OpenSSL Cryptographic Key (Base64 encoded, 256-bit)");
-            println!("    [2] Ring Cryptographic Key    (Base64 encoded, 256-bit)");
-            println!("    [3] UUID                      (v4 format)");
-            println!("    [4] API Key                   (Base64 encoded, 256-bit)");
-            println!("    [5] Password                  (16-characters)");
-            println!("    [6] Password                  (32-characters)");
-            println!("    [7] Username                  (Word list generated)");
-            println!("    [8] Encrypt file              (AES 256-bit)");
-            println!("    [9] Decrypt file              (AES 256-bit)");
-            println!("    [q] Quit\n");
-            println!("________________________________\n");
-
-		    enable_raw_mode().unwrap();
-
-            print!("\nPress a key (1-7, q): ");
-            io::stdout().flush().unwrap();
-
-            let key_event = get_keypress().unwrap();
-            match key_event.code {
-                KeyCode::Char('q') => {
-                    println!("\n\x1b[92mGoodbye!\x1b[0m\n");
-                    disable_raw_mode().unwrap();
-                    return;
-                }
-                KeyCode::Char(c) if c.is_digit(10) => {
-                    let choice = c.to_digit(10).unwrap();
-                    if choice >= 1 && choice <= 7 {
-                        if let Some(k) = generate_key(choice) {
-                            k
-                        } else {
-                            println!("\x1b[91mInvalid choice!\x1b[0m");
-                            continue;
-                        }
-                    } else if choice == 8 {
-                        lunchbox::encrypt_menu_loop();
-                        continue;
-                    } else if choice == 9 {
-                        lunchbox::decrypt_menu_loop();
-                        continue;
-                    } else {
-                        println!("\x1b[91mInvalid choice!\x1b[0m");
-                        continue;
-                    }
-                }
-                _ => {
-                    println!("\x1b[91mInvalid input! Press a number (1-9) or 'q' to quit.\x1b[0m");
-                    continue;
-                }
+        ui.screen("Main menu");
+        for skipped in skipped {
+            say!(ui, "{}", paint(Tone::Warning, format_args!("Skipped banner {}", skipped)));
+        }
+        let width = ui.body_width();
+        match &banner {
+            // Banners are only drawn on the full screen, centred, when they leave room for the menu
+            Ok(Some(banner)) if !ui.is_plain() && banner.width() <= width && banner.height() + skipped.len() + items.len() + 3 <= ui.body_height() => {
+                ui.say(banner.lines((width - banner.width()) / 2).join("\n"));
+                let indent = width.saturating_sub(banner.greeting.chars().count()) / 2;
+                say!(ui, "\n{}{}", " ".repeat(indent), paint(Tone::Success, &banner.greeting));
             }
+            Ok(_) => say!(ui, "\n    {}", paint(Tone::Success, banner::GREETING)),
+            Err(e) => {
+                say!(ui, "{}", paint(Tone::Error, format_args!("Failed to load the banner: {}", e)));
+                say!(ui, "\n    {}", paint(Tone::Success, banner::GREETING));
+            }
+        }
+
+        let Some(choice) = ui.choose("", &items, selected)? else {
+            continue;
         };
+        selected = choice;
+        let next = match (MENU.iter().find(|entry| entry.key == choice), keyed.iter().find(|(_, key)| *key == choice)) {
+            (Some(entry), _) => (entry.open)(ui, config)?,
+            (None, Some((preset, _))) => key_screen(ui, config, (*preset).clone())?,
+            (None, None) => Next::Menu,
+        };
+        if next == Next::Quit {
+            return Ok(());
+        }
+    }
+}
 
-        println!("\n________________________________\n");
-        println!("\x1b[92mGenerated Key:\x1b[0m \x1b[38;5;198m({})\x1b[0m", key_type);
-        println!("\x1b[96m{}\x1b[0m\n", key);
-
-        loop {
-            println!("\nOptions:");
-            println!("    [c] Copy to clipboard");
-            println!("    [r] Regenerate");
-            println!("    [m] Main menu");
-            println!("    [q] Exit the app\n");
-
-            print!("\nPress a key (c, r, m, q): ");
-            io::stdout().flush().unwrap();
-
-            let option_event = get_keypress().unwrap();
-            match option_event.code {
-                KeyCode::Char('c') => {
-                    if crypto_control::copy_to_clipboard(&key) {
-                        println!("\x1b[92mKey copied to clipboard successfully!\x1b[0m");
-                    } else {
-                        println!("\x1b[91mFailed to copy key to clipboard.\x1b[0m");
-                    }
-                }
-                KeyCode::Char('r') => {
-                    if let Some((new_key, new_key_type, _)) = generate_key(key_choice) {
-                        println!("\n________________________________\n");
-                        println!("\x1b[92mRegenerated Key:\x1b[0m \x1b[38;5;198m({})\x1b[0m", new_key_type);
-                        println!("\x1b[96m{}\x1b[0m\n", new_key);
-                    } else {
-                        println!("\x1b[91mError in regenerating key!\x1b[0m");
-                    }
-                }
-                KeyCode::Char('m') => break,
-                KeyCode::Char('q') => {
-                    println!("\n\x1b[92mGoodbye!\x1b[0m\n");
-                    disable_raw_mode().unwrap();
-                    return;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn args(flags: &[&str]) -> Args {
+        Args::parse_from(std::iter::once("key-gen-app").chain(flags.iter().copied()))
+    }
+
+    fn configured() -> Config {
+        let mut config = Config::default();
+        config.clipboard.timeout = 10;
+        config.clipboard.backend = clipboard::BackendChoice::Xsel;
+        config.display.color = ColorChoice::Always;
+        config
+    }
+
+    #[test]
+    fn the_config_applies_without_flags() {
+        let settings = clipboard_settings(&args(&[]), &configured());
+        assert_eq!(settings.clear_after, Some(Duration::from_secs(10)));
+        assert_eq!(settings.backend, clipboard::BackendChoice::Xsel);
+        assert!(!settings.exclude_from_history);
+        assert_eq!(display_settings(&args(&[]), &configured()), (ColorChoice::Always, true));
+    }
+
+    #[test]
+    fn flags_override_the_config() {
+        let flags = args(&["--clipboard-timeout", "0", "--clipboard", "osc52", "--no-clipboard-history"]);
+        let settings = clipboard_settings(&flags, &configured());
+        assert_eq!(settings.clear_after, None);
+        assert_eq!(settings.backend, clipboard::BackendChoice::Osc52);
+        assert!(settings.exclude_from_history);
+        assert_eq!(display_settings(&args(&["--color", "never", "--no-emoji"]), &configured()), (ColorChoice::Never, false));
+    }
+
+    #[test]
+    fn plain_turns_off_colour_unless_asked_for() {
+        let mut config = Config::default();
+        config.display.plain = true;
+        assert_eq!(display_settings(&args(&[]), &config), (ColorChoice::Never, false));
+        assert_eq!(display_settings(&args(&["--color", "always"]), &config), (ColorChoice::Always, false));
+    }
+
+    #[test]
+    fn menu_keys_never_clash() {
+        // However many presets there are, each key opens one entry
+        let mut keys = MENU.iter().map(|entry| entry.key).chain(preset_keys()).collect::<Vec<_>>();
+        let count = keys.len();
+        assert!(count >= MENU.len() + generator::presets(&Defaults::BUILT_IN).len() + 10);
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), count);
+        assert_eq!(preset_keys().take(8).collect::<String>(), "1234567b");
+    }
+
+    #[test]
+    fn each_short_flag_has_one_meaning() {
+        use clap::CommandFactory;
+
+        // -k is the key type, -K a key file to read, -O a key file to write
+        let app = Args::command();
+        assert_eq!(app.get_arguments().find(|arg| arg.get_short() == Some('k')).map(|arg| arg.get_id().as_str()), Some("key"));
+        for command in app.get_subcommands() {
+            for arg in command.get_arguments() {
+                let expected = match arg.get_long() {
+                    Some("key-file") => Some('K'),
+                    Some("key-out") => Some('O'),
+                    _ => None,
+                };
+                assert_ne!(arg.get_short(), Some('k'), "{} {:?}", command.get_name(), arg.get_long());
+                if expected.is_some() {
+                    assert_eq!(arg.get_short(), expected, "{} {:?}", command.get_name(), arg.get_long());
                 }
-                _ => println!("\x1b[91mInvalid option! Press 'c', 'r', 'm', or 'q'.\x1b[0m"),
             }
         }
     }
 }
-
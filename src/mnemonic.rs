@@ -0,0 +1,211 @@
+// mnemonic.rs
+
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+use crate::error::{Error, Result};
+
+// Compiled in rather than read at runtime: losing or editing this list would make written-down words unreadable
+const WORDLIST: &str = include_str!("words/bip39_english.txt");
+
+/// The 2048-word BIP39 English wordlist, each word standing for 11 bits
+pub fn wordlist() -> &'static [&'static str] {
+    static WORDS: OnceLock<Vec<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| WORDLIST.lines().collect())
+}
+
+/// Spells out bytes as words, 11 bits per word, zero-padding the last word
+pub fn encode_bytes(data: &[u8]) -> String {
+    let words = wordlist();
+    let mut out = Vec::with_capacity((data.len() * 8).div_ceil(11));
+    let (mut acc, mut bits) = (0u32, 0);
+    for &byte in data {
+        acc = (acc << 8) | byte as u32;
+        bits += 8;
+        while bits >= 11 {
+            bits -= 11;
+            out.push(words[((acc >> bits) & 0x7ff) as usize]);
+        }
+    }
+    if bits > 0 {
+        out.push(words[((acc << (11 - bits)) & 0x7ff) as usize]);
+    }
+    out.join(" ")
+}
+
+// Edit distance between two words, to suggest corrections for typos
+fn distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// Index of a word in the list. Like other BIP39 tools, the first four letters are
+// enough since they are unique. Unknown words get the closest matches as suggestions.
+fn lookup(word: &str, position: usize) -> Result<usize> {
+    let words = wordlist();
+    let word = word.to_lowercase();
+    if let Ok(index) = words.binary_search(&word.as_str()) {
+        return Ok(index);
+    }
+
+    let prefix = word.chars().take(4).collect::<String>();
+    let matches = words.iter().filter(|w| w.starts_with(&prefix)).collect::<Vec<_>>();
+    if word.chars().count() >= 4 && matches.len() == 1 && matches[0].starts_with(&word) {
+        return Ok(words.binary_search(matches[0]).unwrap());
+    }
+
+    let mut candidates = words
+        .iter()
+        .map(|w| (distance(&word, w), *w))
+        .filter(|(d, _)| *d <= 2)
+        .collect::<Vec<_>>();
+    candidates.sort();
+    let suggestions = candidates.iter().take(5).map(|(_, w)| *w).collect::<Vec<_>>();
+    if suggestions.is_empty() {
+        Err(Error::InvalidInput(format!("Unknown word \"{}\" (word {}).", word, position + 1)))
+    } else {
+        Err(Error::InvalidInput(format!(
+            "Unknown word \"{}\" (word {}). Did you mean: {}?",
+            word,
+            position + 1,
+            suggestions.join(", ")
+        )))
+    }
+}
+
+// Packs the words' 11-bit indexes into bytes. With `flush`, leftover bits form a final left-aligned byte.
+fn pack(phrase: &str, flush: bool) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut acc, mut bits) = (0u32, 0);
+    for (position, word) in phrase.split_whitespace().enumerate() {
+        let index = lookup(word, position)?;
+        acc = (acc << 11) | index as u32;
+        bits += 11;
+        while bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    if flush && bits > 0 {
+        out.push((acc << (8 - bits)) as u8);
+    }
+    Ok(out)
+}
+
+/// Reads words back into bytes. Padding bits are dropped, so the result can carry
+/// one extra zero byte that the caller's own length or checksum has to rule out.
+pub fn decode_bytes(phrase: &str) -> Result<Vec<u8>> {
+    pack(phrase, false)
+}
+
+/// Encodes a 128 to 256-bit key as a BIP39 mnemonic (12 to 24 words), with the
+/// standard SHA-256 checksum in the last word so typos are caught when it's read back
+pub fn encode_key(key: &[u8]) -> Result<String> {
+    if !(16..=32).contains(&key.len()) || !key.len().is_multiple_of(4) {
+        return Err(Error::InvalidInput("Only 128 to 256-bit keys (in 32-bit steps) can be written as words.".into()));
+    }
+    let checksum_bits = key.len() / 4;
+    let mut data = key.to_vec();
+    data.push(Sha256::digest(key)[0]);
+
+    // The checksum byte only contributes its top `checksum_bits` bits
+    let words = encode_bytes(&data);
+    let count = (key.len() * 8 + checksum_bits) / 11;
+    Ok(words.split(' ').take(count).collect::<Vec<_>>().join(" "))
+}
+
+/// Decodes a BIP39 mnemonic back into the key, checking its checksum
+pub fn decode_key(phrase: &str) -> Result<Vec<u8>> {
+    let count = phrase.split_whitespace().count();
+    if ![12, 15, 18, 21, 24].contains(&count) {
+        return Err(Error::InvalidInput(format!("A key mnemonic has 12, 15, 18, 21 or 24 words, not {}.", count)));
+    }
+
+    let mut data = pack(phrase, true)?;
+    let key_len = count * 4 / 3;
+    let checksum_bits = key_len / 4;
+    // The byte after the key starts with the checksum bits
+    let stored = data[key_len] >> (8 - checksum_bits);
+    data.truncate(key_len);
+
+    if Sha256::digest(&data)[0] >> (8 - checksum_bits) != stored {
+        return Err(Error::InvalidInput("The words don't add up: one is mistyped, swapped or missing (checksum mismatch).".into()));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Entropy and mnemonic pairs from the BIP39 reference test vectors (trezor/python-mnemonic)
+    const VECTORS: &[(&str, &str)] = &[
+        ("00000000000000000000000000000000", "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"),
+        ("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f", "legal winner thank year wave sausage worth useful legal winner thank yellow"),
+        ("80808080808080808080808080808080", "letter advice cage absurd amount doctor acoustic avoid letter advice cage above"),
+        ("ffffffffffffffffffffffffffffffff", "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong"),
+        ("000000000000000000000000000000000000000000000000", "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon agent"),
+        ("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f", "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal will"),
+        ("808080808080808080808080808080808080808080808080", "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter always"),
+        ("ffffffffffffffffffffffffffffffffffffffffffffffff", "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo when"),
+        ("0000000000000000000000000000000000000000000000000000000000000000", "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art"),
+        ("7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f", "legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth useful legal winner thank year wave sausage worth title"),
+        ("8080808080808080808080808080808080808080808080808080808080808080", "letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic avoid letter advice cage absurd amount doctor acoustic bless"),
+        ("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff", "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote"),
+        ("9e885d952ad362caeb4efe34a8e91bd2", "ozone drill grab fiber curtain grace pudding thank cruise elder eight picnic"),
+        ("6610b25967cdcca9d59875f5cb50b0ea75433311869e930b", "gravity machine north sort system female filter attitude volume fold club stay feature office ecology stable narrow fog"),
+        ("68a79eaca2324873eacc50cb9c6eca8cc68ea5d936f98787c60c7ebc74e6ce7c", "hamster diagram private dutch cause delay private meat slide toddler razor book happy fancy gospel tennis maple dilemma loan word shrug inflict delay length"),
+        ("c0ba5a8e914111210f2bd131f3d5e08d", "scheme spot photo card baby mountain device kick cradle pact join borrow"),
+        ("f585c11aec520db57dd353c69554b21a89b20fb0650966fa0a9d6f74fd989d8f", "void come effort suffer camp survey warrior heavy shoot primary clutch crush open amazing screen patrol group space point ten exist slush involve unfold"),
+    ];
+
+    #[test]
+    fn encodes_the_reference_vectors() {
+        for (entropy, phrase) in VECTORS {
+            assert_eq!(encode_key(&hex::decode(entropy).unwrap()).unwrap(), *phrase);
+        }
+    }
+
+    #[test]
+    fn decodes_the_reference_vectors() {
+        for (entropy, phrase) in VECTORS {
+            assert_eq!(hex::encode(decode_key(phrase).unwrap()), *entropy);
+        }
+    }
+
+    #[test]
+    fn catches_a_swapped_word() {
+        let phrase = "legal winner thank year wave sausage worth useful legal winner yellow thank";
+        assert!(matches!(decode_key(phrase), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn accepts_four_letter_prefixes_and_suggests_fixes() {
+        let key = decode_key("ozon dril grab fibe curt grac pudd than crui elde eigh picn").unwrap();
+        assert_eq!(hex::encode(key), "9e885d952ad362caeb4efe34a8e91bd2");
+
+        let Err(Error::InvalidInput(message)) = decode_key("ozone drill grab fiber curtain grace pudding thank cruise elder eight picnix")
+        else {
+            panic!("a misspelt word was accepted");
+        };
+        assert!(message.contains("picnic"), "{}", message);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        for len in 0..40 {
+            let data = (0..len as u8).map(|b| b.wrapping_mul(37)).collect::<Vec<_>>();
+            let decoded = decode_bytes(&encode_bytes(&data)).unwrap();
+            assert!(decoded == data || decoded.strip_suffix(&[0]) == Some(&data[..]), "{} bytes", len);
+        }
+    }
+}
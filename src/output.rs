@@ -0,0 +1,186 @@
+// output.rs
+
+use clap::ValueEnum;
+use rand::Rng;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+
+/// What to do when an output path is already taken
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Collision {
+    /// Ask the user (see `resolve`)
+    Ask,
+    /// Replace the existing file
+    Overwrite,
+    /// Keep both, writing to the next free "name-N" path
+    Rename,
+    /// Refuse and report an error
+    Fail,
+}
+
+fn exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+/// Next unused path of the form "name-1.ext", "name-2.ext", ...
+pub fn free_name(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path.extension().map(|e| format!(".{}", e.to_string_lossy())).unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{}-{}{}", stem, n, ext)))
+        .find(|candidate| !exists(candidate))
+        .unwrap()
+}
+
+/// Decides where to write `path` if something is already there.
+/// `ask` handles `Collision::Ask`, given the path and a free alternative.
+/// Returns `None` when the user cancels.
+pub fn resolve(
+    path: &Path,
+    policy: Collision,
+    ask: impl FnOnce(&Path, PathBuf) -> io::Result<Option<PathBuf>>,
+) -> Result<Option<PathBuf>> {
+    if !exists(path) {
+        return Ok(Some(path.to_path_buf()));
+    }
+
+    match policy {
+        Collision::Overwrite => Ok(Some(path.to_path_buf())),
+        Collision::Rename => Ok(Some(free_name(path))),
+        Collision::Fail => Err(Error::Io(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists (use --force to overwrite or --rename to keep both).", path.display()),
+        ))),
+        Collision::Ask => Ok(ask(path, free_name(path))?),
+    }
+}
+
+// Hidden sibling of `path` for staging writes; same directory, so renaming it is atomic
+pub(crate) fn staging_path(path: &Path, label: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let tag: u32 = rand::thread_rng().r#gen();
+    path.with_file_name(format!(".{}.{:08x}.{}", name, tag, label))
+}
+
+/// Writes to a temporary file and renames it into place, so an interrupted run
+/// never leaves a half-written output. Private files are created owner-only (0600).
+pub fn write_atomic(path: &Path, data: &[u8], private: bool) -> io::Result<()> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = staging_path(path, "tmp");
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    #[cfg(not(unix))]
+    let _ = private;
+
+    let result = options.open(&tmp).and_then(|mut file| {
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    fn never_asked(_: &Path, _: PathBuf) -> io::Result<Option<PathBuf>> {
+        panic!("asked about a path that was free")
+    }
+
+    #[test]
+    fn numbers_collisions_from_one() {
+        let tmp = TempDir::new("output-free-name");
+        let key = tmp.file("key.bin", "");
+        assert_eq!(free_name(&key), tmp.path().join("key-1.bin"));
+        tmp.file("key-1.bin", "");
+        tmp.file("key-3.bin", "");
+        assert_eq!(free_name(&key), tmp.path().join("key-2.bin"));
+        // Only the last extension moves, and a name without one is numbered at the end
+        assert_eq!(free_name(&tmp.path().join("notes.tar.gz")), tmp.path().join("notes.tar-1.gz"));
+        assert_eq!(free_name(&tmp.path().join("README")), tmp.path().join("README-1"));
+    }
+
+    #[test]
+    fn directories_and_dangling_links_are_taken_too() {
+        let tmp = TempDir::new("output-taken");
+        let dir = tmp.path().join("photos");
+        fs::create_dir(&dir).unwrap();
+        assert_eq!(resolve(&dir, Collision::Rename, never_asked).unwrap(), Some(tmp.path().join("photos-1")));
+        #[cfg(unix)]
+        {
+            let link = tmp.path().join("gone.txt");
+            std::os::unix::fs::symlink(tmp.path().join("missing"), &link).unwrap();
+            assert_eq!(free_name(&link), tmp.path().join("gone-1.txt"));
+        }
+    }
+
+    #[test]
+    fn resolves_by_policy() {
+        let tmp = TempDir::new("output-resolve");
+        let free = tmp.path().join("free.txt");
+        for policy in [Collision::Ask, Collision::Overwrite, Collision::Rename, Collision::Fail] {
+            assert_eq!(resolve(&free, policy, never_asked).unwrap(), Some(free.clone()));
+        }
+
+        let taken = tmp.file("taken.txt", "keep me");
+        assert_eq!(resolve(&taken, Collision::Overwrite, never_asked).unwrap(), Some(taken.clone()));
+        assert_eq!(resolve(&taken, Collision::Rename, never_asked).unwrap(), Some(tmp.path().join("taken-1.txt")));
+        match resolve(&taken, Collision::Fail, never_asked) {
+            Err(Error::Io(e)) => assert_eq!(e.kind(), io::ErrorKind::AlreadyExists),
+            other => panic!("expected a refusal, got {:?}", other),
+        }
+
+        let asked = resolve(&taken, Collision::Ask, |path, alternative| {
+            assert_eq!((path, alternative.as_path()), (taken.as_path(), tmp.path().join("taken-1.txt").as_path()));
+            Ok(None)
+        });
+        assert_eq!(asked.unwrap(), None);
+        assert_eq!(fs::read_to_string(&taken).unwrap(), "keep me");
+    }
+
+    #[test]
+    fn writes_atomically() {
+        let tmp = TempDir::new("output-write");
+        let path = tmp.path().join("new").join("dir").join("out.txt");
+        write_atomic(&path, b"first", false).unwrap();
+        write_atomic(&path, b"second", false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+
+        // A directory in the way isn't replaced, and no staging file is left behind
+        let dir = tmp.path().join("new").join("dir");
+        fs::create_dir(dir.join("blocked")).unwrap();
+        assert!(write_atomic(&dir.join("blocked"), b"data", false).is_err());
+        let names = fs::read_dir(&dir).unwrap().map(|e| e.unwrap().file_name()).collect::<Vec<_>>();
+        assert_eq!(names.len(), 2, "{:?}", names);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn private_files_are_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = TempDir::new("output-private");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        let path = tmp.file("key.txt", "old");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        write_atomic(&path, b"secret", true).unwrap();
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(fs::read(&path).unwrap(), b"secret");
+    }
+}
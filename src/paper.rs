@@ -0,0 +1,234 @@
+// paper.rs
+
+use qrcode::{Color, EcLevel, QrCode};
+use sha2::{Digest, Sha256};
+use std::time::SystemTime;
+
+use crate::container;
+use crate::error::{Error, Result};
+use crate::keyfile::KEY_LEN;
+use crate::mnemonic;
+
+// Light modules around the code, as the QR spec asks for
+const QUIET_ZONE: usize = 4;
+
+/// Everything printed on a paper backup sheet
+pub struct Backup {
+    /// The key (or other generated secret) exactly as it's typed back in
+    pub secret: String,
+    /// File the key was used for, if any
+    pub file_name: Option<String>,
+    pub created: SystemTime,
+}
+
+/// Output format of a backup sheet
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SheetFormat {
+    Text,
+    Svg,
+}
+
+impl SheetFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SheetFormat::Text => "txt",
+            SheetFormat::Svg => "svg",
+        }
+    }
+}
+
+impl Backup {
+    pub fn new(secret: &str, file_name: Option<String>) -> Backup {
+        Backup { secret: secret.to_string(), file_name, created: SystemTime::now() }
+    }
+
+    // The raw key when the secret is a Base64 file key, so key-only details can be shown
+    fn key(&self) -> Option<Vec<u8>> {
+        base64::decode(&self.secret).ok().filter(|k| k.len() == KEY_LEN)
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        self.key().unwrap_or_else(|| self.secret.as_bytes().to_vec())
+    }
+
+    // Labelled lines shared by the text and SVG sheets
+    fn details(&self) -> Vec<(String, String)> {
+        let mut details = Vec::new();
+        if let Some(key) = self.key() {
+            details.push(("Key ID".to_string(), container::key_id_hex(&container::key_id(&key))));
+        }
+        details.push(("Created".to_string(), humantime::format_rfc3339_seconds(self.created).to_string()));
+        details.push(("File".to_string(), self.file_name.clone().unwrap_or_else(|| "-".to_string())));
+        details
+    }
+
+    /// Hex in groups of four, eight groups per line, each line ending with its own
+    /// check byte so a misread line can be found, then a checksum of the whole secret
+    pub fn grouped_hex(&self) -> Vec<String> {
+        let bytes = self.bytes();
+        let mut lines = bytes
+            .chunks(16)
+            .map(|chunk| {
+                let groups = hex::encode(chunk)
+                    .as_bytes()
+                    .chunks(4)
+                    .map(|g| String::from_utf8_lossy(g).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                format!("{:<39}  [{:02x}]", groups, Sha256::digest(chunk)[0])
+            })
+            .collect::<Vec<_>>();
+        let checksum = hex::encode(&Sha256::digest(&bytes)[..4]);
+        lines.push(format!("Checksum (SHA-256): {} {}", &checksum[..4], &checksum[4..]));
+        lines
+    }
+
+    fn words(&self) -> Option<Vec<String>> {
+        let words = mnemonic::encode_key(&self.key()?).ok()?;
+        let words = words.split(' ').enumerate().map(|(i, w)| format!("{:>2}. {:<8}", i + 1, w)).collect::<Vec<_>>();
+        Some(words.chunks(6).map(|row| row.join("  ").trim_end().to_string()).collect())
+    }
+
+    fn qr(&self) -> Result<QrCode> {
+        QrCode::with_error_correction_level(self.secret.as_bytes(), EcLevel::M)
+            .map_err(|e| Error::InvalidInput(format!("Failed to create QR code: {}", e)))
+    }
+
+    /// Plain text sheet; the QR code is drawn with block characters, so print it in a monospace font
+    pub fn text_sheet(&self) -> Result<String> {
+        let mut out = String::new();
+        out.push_str("CRYPTO-BRO KEY BACKUP\n=====================\n\n");
+        for (label, value) in self.details() {
+            out.push_str(&format!("{:<8} {}\n", format!("{}:", label), value));
+        }
+
+        out.push_str(&format!("\n{}:\n  {}\n", if self.key().is_some() { "Key (Base64)" } else { "Secret" }, self.secret));
+        out.push_str("\nHex:\n");
+        for line in self.grouped_hex() {
+            out.push_str(&format!("  {}\n", line));
+        }
+        if let Some(rows) = self.words() {
+            out.push_str("\nWords (BIP39):\n");
+            for row in rows {
+                out.push_str(&format!("  {}\n", row));
+            }
+        }
+
+        out.push_str("\nQR code:\n\n");
+        out.push_str(&text_qr(&self.qr()?));
+        out.push_str("\nKeep this sheet offline and somewhere safe. Anyone holding it can decrypt your files.\n");
+        Ok(out)
+    }
+
+    /// A4 SVG sheet, ready to print from a browser or image viewer
+    pub fn svg_sheet(&self) -> Result<String> {
+        let mut lines = vec![("title", "CRYPTO-BRO KEY BACKUP".to_string()), ("gap", String::new())];
+        for (label, value) in self.details() {
+            lines.push(("text", format!("{:<8} {}", format!("{}:", label), value)));
+        }
+        lines.push(("gap", String::new()));
+        lines.push(("heading", if self.key().is_some() { "Key (Base64)" } else { "Secret" }.to_string()));
+        lines.push(("text", self.secret.clone()));
+        lines.push(("gap", String::new()));
+        lines.push(("heading", "Hex".to_string()));
+        lines.extend(self.grouped_hex().into_iter().map(|l| ("text", l)));
+        if let Some(rows) = self.words() {
+            lines.push(("gap", String::new()));
+            lines.push(("heading", "Words (BIP39)".to_string()));
+            lines.extend(rows.into_iter().map(|r| ("text", r)));
+        }
+
+        let mut svg = String::from(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"210mm\" height=\"297mm\" viewBox=\"0 0 210 297\">\n\
+             <rect width=\"210\" height=\"297\" fill=\"#fff\"/>\n",
+        );
+        let mut y = 20.0;
+        for (style, text) in &lines {
+            let (size, weight) = match *style {
+                "title" => (7.0, "bold"),
+                "heading" => (4.0, "bold"),
+                "gap" => {
+                    y += 3.0;
+                    continue;
+                }
+                _ => (3.6, "normal"),
+            };
+            svg.push_str(&format!(
+                "<text x=\"15\" y=\"{:.1}\" font-family=\"monospace\" font-size=\"{}\" font-weight=\"{}\" xml:space=\"preserve\">{}</text>\n",
+                y,
+                size,
+                weight,
+                escape_xml(text)
+            ));
+            y += size * 1.5;
+        }
+
+        let qr = self.qr()?;
+        let size = 70.0;
+        svg.push_str(&svg_qr(&qr, 15.0, y + 5.0, size));
+        svg.push_str(&format!(
+            "<text x=\"15\" y=\"{:.1}\" font-family=\"sans-serif\" font-size=\"3.5\">Keep this sheet offline and somewhere safe. Anyone holding it can decrypt your files.</text>\n",
+            y + size + 15.0
+        ));
+        svg.push_str("</svg>\n");
+        Ok(svg)
+    }
+
+    pub fn render(&self, format: SheetFormat) -> Result<String> {
+        match format {
+            SheetFormat::Text => self.text_sheet(),
+            SheetFormat::Svg => self.svg_sheet(),
+        }
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+// Whether the module at (x, y) is dark, treating the quiet zone as light
+fn is_dark(colors: &[Color], width: usize, x: usize, y: usize) -> bool {
+    let (x, y) = (x.wrapping_sub(QUIET_ZONE), y.wrapping_sub(QUIET_ZONE));
+    x < width && y < width && colors[y * width + x] == Color::Dark
+}
+
+// Two rows of modules per line of half-block characters, dark on a light page
+fn text_qr(code: &QrCode) -> String {
+    let (width, colors) = (code.width(), code.to_colors());
+    let size = width + 2 * QUIET_ZONE;
+    let mut out = String::new();
+    for y in (0..size).step_by(2) {
+        out.push_str("  ");
+        for x in 0..size {
+            out.push(match (is_dark(&colors, width, x, y), is_dark(&colors, width, x, y + 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            });
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// One path of unit squares for the dark modules, scaled to `size` at (x, y)
+fn svg_qr(code: &QrCode, x: f64, y: f64, size: f64) -> String {
+    let (width, colors) = (code.width(), code.to_colors());
+    let total = width + 2 * QUIET_ZONE;
+    let mut path = String::new();
+    for row in 0..total {
+        for col in 0..total {
+            if is_dark(&colors, width, col, row) {
+                path.push_str(&format!("M{} {}h1v1h-1z", col, row));
+            }
+        }
+    }
+    format!(
+        "<g transform=\"translate({} {}) scale({:.4})\"><path d=\"{}\" fill=\"#000\"/></g>\n",
+        x,
+        y,
+        size / total as f64,
+        path
+    )
+}
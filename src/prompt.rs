@@ -0,0 +1,88 @@
+// prompt.rs
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEventKind, KeyModifiers},
+    terminal::{disable_raw_mode, enable_raw_mode},
+};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use crate::style::{self, paint, Tone};
+
+/// Reads a line of input, without its line ending. A closed stdin is an error,
+/// so menus stop instead of looping on empty input forever.
+pub fn read_line() -> io::Result<String> {
+    let mut input = String::new();
+    if io::stdin().read_line(&mut input)? == 0 {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Input closed."));
+    }
+    Ok(input.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Reads a line of input without echoing it to the terminal.
+/// The prompt goes to stderr so it never mixes with output piped from stdout.
+pub fn read_hidden(prompt: &str) -> io::Result<String> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    // Piped input (scripts, CI) has nothing to hide, read it as a plain line
+    if !io::stdin().is_terminal() {
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        return Ok(input.trim_end_matches(['\r', '\n']).to_string());
+    }
+
+    enable_raw_mode()?;
+    let mut input = String::new();
+    let result = loop {
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind != KeyEventKind::Release => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(io::Error::new(io::ErrorKind::Interrupted, "Input cancelled"));
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+    };
+    disable_raw_mode()?;
+    eprintln!();
+
+    result.map(|_| input)
+}
+
+/// Reads a new passphrase twice without echoing it, failing if the two entries differ
+pub fn read_new_passphrase(prompt: &str) -> io::Result<String> {
+    let pass = read_hidden(prompt)?;
+    let confirm = read_hidden("Confirm passphrase: ")?;
+    if pass != confirm {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Passphrases do not match."));
+    }
+    Ok(pass)
+}
+
+/// Asks what to do about an output that already exists, for `output::resolve`.
+/// Returns where to write, or `None` to cancel.
+pub fn ask_collision(path: &Path, alternative: PathBuf) -> io::Result<Option<PathBuf>> {
+    loop {
+        println!("\n{}", paint(Tone::Warning, format_args!("{}{} already exists.", style::text("⚠️  "), path.display())));
+        println!("  [o] Overwrite it");
+        println!("  [r] Keep both (save as {})", alternative.display());
+        println!("  [c] Cancel");
+        print!("\nType option (o, r, c) and press [Enter]: ");
+        io::stdout().flush()?;
+
+        match read_line()?.trim() {
+            "o" => return Ok(Some(path.to_path_buf())),
+            "r" => return Ok(Some(alternative)),
+            "c" => return Ok(None),
+            _ => println!("{}", paint(Tone::Error, "Invalid input.")),
+        }
+    }
+}
@@ -0,0 +1,352 @@
+// shamir.rs
+
+use clap::ValueEnum;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::io;
+
+use crate::error::{Error, Result};
+use crate::keyfile::{self, KEY_LEN};
+use crate::mnemonic;
+
+// Shamir's secret sharing over GF(2^8), one polynomial per secret byte.
+//
+// Binary share: version | set ID (2) | threshold | index | share bytes | SHA-256 check (4)
+// The set ID keeps shares of different splits apart, and the check catches typos in a share.
+//
+// The shared secret is: kind | secret | SHA-256 check (4). Its check is only
+// visible after recombining, so it tells a good result from a wrong set of shares
+// without revealing anything about the secret to someone holding too few shares.
+const SHARE_VERSION: u8 = 1;
+const CHECK_LEN: usize = 4;
+const SHARE_OVERHEAD: usize = 5 + CHECK_LEN;
+
+/// What was split, so it can be given back in the same form
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SecretKind {
+    /// Free text, e.g. a password or recovery phrase
+    Text = 0,
+    /// A 256-bit file encryption key
+    Key = 1,
+}
+
+/// How shares are written out
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ShareEncoding {
+    /// Base64, one short line per share
+    Text,
+    /// Hexadecimal
+    Hex,
+    /// BIP39 English words, easiest to write down by hand
+    Mnemonic,
+}
+
+pub struct Share {
+    pub set_id: [u8; 2],
+    pub threshold: u8,
+    pub index: u8,
+    pub data: Vec<u8>,
+}
+
+fn check(data: &[u8]) -> [u8; CHECK_LEN] {
+    Sha256::digest(data)[..CHECK_LEN].try_into().unwrap()
+}
+
+// Multiplication in GF(2^8) with the AES polynomial, without secret-dependent branches
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & 0u8.wrapping_sub(b & 1);
+        let carry = 0u8.wrapping_sub(a >> 7);
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+// a^254 is the inverse of a in GF(2^8)
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Treats input that is a 256-bit key in Base64, or a key file's contents, as a key and
+/// anything else as text. Words stay text, even when they spell a key: a recovery phrase
+/// is given back as the phrase that was split.
+pub fn classify(input: &str) -> (SecretKind, Vec<u8>) {
+    let first_line = input.lines().map(str::trim).find(|l| !l.is_empty() && !l.starts_with('#'));
+    if first_line.is_some_and(|line| line.split_whitespace().nth(1).is_some()) {
+        return (SecretKind::Text, input.as_bytes().to_vec());
+    }
+    match keyfile::decode(input, || Err(io::ErrorKind::Unsupported.into())) {
+        Ok(key) if key.len() == KEY_LEN => (SecretKind::Key, key),
+        _ => (SecretKind::Text, input.as_bytes().to_vec()),
+    }
+}
+
+/// Splits a secret into `shares` shares, any `threshold` of which recover it
+pub fn split(secret: &[u8], kind: SecretKind, threshold: u8, shares: u8) -> Result<Vec<Share>> {
+    if threshold < 2 || threshold > shares {
+        return Err(Error::InvalidInput("The threshold must be at least 2 and no more than the number of shares.".into()));
+    }
+    if secret.is_empty() {
+        return Err(Error::InvalidInput("Secret is empty.".into()));
+    }
+
+    let mut payload = Vec::with_capacity(1 + secret.len() + CHECK_LEN);
+    payload.push(kind as u8);
+    payload.extend_from_slice(secret);
+    payload.extend_from_slice(&check(&payload));
+
+    let mut rng = rand::thread_rng();
+    let mut set_id = [0u8; 2];
+    rng.fill_bytes(&mut set_id);
+
+    let mut out = (1..=shares)
+        .map(|index| Share { set_id, threshold, index, data: Vec::with_capacity(payload.len()) })
+        .collect::<Vec<_>>();
+    let mut coefficients = vec![0u8; threshold as usize];
+    for &byte in &payload {
+        coefficients[0] = byte;
+        rng.fill_bytes(&mut coefficients[1..]);
+        for share in &mut out {
+            // Horner's rule, highest coefficient first
+            let y = coefficients.iter().rev().fold(0u8, |acc, &c| gf_mul(acc, share.index) ^ c);
+            share.data.push(y);
+        }
+    }
+    coefficients.iter_mut().for_each(|c| *c = 0);
+    Ok(out)
+}
+
+/// Recombines shares into the secret, checking they belong together and that the result is intact
+pub fn combine(shares: &[Share]) -> Result<(SecretKind, Vec<u8>)> {
+    let first = shares.first().ok_or_else(|| Error::InvalidInput("No shares given.".into()))?;
+    if shares.iter().any(|s| s.set_id != first.set_id) {
+        return Err(Error::InvalidInput("These shares come from different splits.".into()));
+    }
+    if shares.iter().any(|s| s.threshold != first.threshold || s.data.len() != first.data.len()) {
+        return Err(Error::InvalidInput("These shares don't match each other.".into()));
+    }
+
+    let mut indexes = shares.iter().map(|s| s.index).collect::<Vec<_>>();
+    indexes.sort_unstable();
+    indexes.dedup();
+    if indexes.len() < first.threshold as usize {
+        return Err(Error::InvalidInput(format!(
+            "{} different share(s) given, but {} are needed.",
+            indexes.len(),
+            first.threshold
+        )));
+    }
+
+    // Lagrange interpolation at x = 0, using exactly `threshold` distinct shares
+    let mut used: Vec<&Share> = Vec::new();
+    for share in shares {
+        if used.len() < first.threshold as usize && !used.iter().any(|s| s.index == share.index) {
+            used.push(share);
+        }
+    }
+    let mut payload = vec![0u8; first.data.len()];
+    for (i, share) in used.iter().enumerate() {
+        let mut basis = 1u8;
+        for (j, other) in used.iter().enumerate() {
+            if i != j {
+                basis = gf_mul(basis, gf_mul(other.index, gf_inv(share.index ^ other.index)));
+            }
+        }
+        for (out, &y) in payload.iter_mut().zip(&share.data) {
+            *out ^= gf_mul(basis, y);
+        }
+    }
+
+    if payload.len() < 1 + CHECK_LEN {
+        return Err(Error::Corrupted("Shares are too short.".into()));
+    }
+    let (body, stored) = payload.split_at(payload.len() - CHECK_LEN);
+    if check(body) != stored {
+        return Err(Error::Corrupted("The recombined secret failed its check: a share is wrong or from another split.".into()));
+    }
+    let kind = match body[0] {
+        0 => SecretKind::Text,
+        1 => SecretKind::Key,
+        _ => return Err(Error::UnsupportedFormat("Unsupported secret type.".into())),
+    };
+    Ok((kind, body[1..].to_vec()))
+}
+
+impl Share {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(SHARE_OVERHEAD + self.data.len());
+        bytes.push(SHARE_VERSION);
+        bytes.extend_from_slice(&self.set_id);
+        bytes.push(self.threshold);
+        bytes.push(self.index);
+        bytes.extend_from_slice(&self.data);
+        bytes.extend_from_slice(&check(&bytes));
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Share> {
+        if bytes.len() <= SHARE_OVERHEAD || bytes[0] != SHARE_VERSION {
+            return None;
+        }
+        let (body, stored) = bytes.split_at(bytes.len() - CHECK_LEN);
+        if check(body) != stored {
+            return None;
+        }
+        Some(Share { set_id: [body[1], body[2]], threshold: body[3], index: body[4], data: body[5..].to_vec() })
+    }
+
+    /// Writes the share as a single line in the given encoding
+    pub fn encode(&self, encoding: ShareEncoding) -> String {
+        let bytes = self.to_bytes();
+        match encoding {
+            ShareEncoding::Text => base64::encode(bytes),
+            ShareEncoding::Hex => hex::encode(bytes),
+            ShareEncoding::Mnemonic => mnemonic::encode_bytes(&bytes),
+        }
+    }
+
+    /// Reads a share in any of the encodings, telling them apart by their shape.
+    /// Words are a mnemonic. A hex share starts with the version byte, "01", while in
+    /// Base64 that byte makes the first character "A", so the start tells those two apart.
+    pub fn decode(line: &str) -> Result<Share> {
+        let line = line.trim();
+        let invalid = || Error::Corrupted("Share is damaged or mistyped (its check doesn't match).".into());
+
+        if line.split_whitespace().nth(1).is_some() {
+            let bytes = mnemonic::decode_bytes(line)?;
+            // Words carry a few padding bits, which may have produced one extra zero byte
+            return Share::from_bytes(&bytes)
+                .or_else(|| bytes.strip_suffix(&[0]).and_then(Share::from_bytes))
+                .ok_or_else(invalid);
+        }
+
+        let bytes = if line.starts_with(&hex::encode([SHARE_VERSION])) {
+            hex::decode(line).map_err(|_| Error::InvalidInput("Share is not valid hex.".into()))?
+        } else {
+            base64::decode(line).map_err(|_| Error::InvalidInput("Share is not valid Base64.".into()))?
+        };
+        Share::from_bytes(&bytes).ok_or_else(invalid)
+    }
+}
+
+/// Reads a share from a file, skipping the "#" comment lines written alongside it
+pub fn share_from_file_contents(contents: &str) -> Result<Share> {
+    let line = contents
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+        .ok_or_else(|| Error::InvalidInput("Share file is empty.".into()))?;
+    Share::decode(line)
+}
+
+/// Contents of a share file for handing out
+pub fn share_file_string(share: &Share, shares: u8, encoding: ShareEncoding) -> String {
+    format!(
+        "# crypto-bro recovery share {} of {}, any {} of them recover the secret\n{}\n",
+        share.index,
+        shares,
+        share.threshold,
+        share.encode(encoding)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: [u8; KEY_LEN] = [42; KEY_LEN];
+
+    fn choose(items: &[Share], k: usize) -> Vec<Vec<&Share>> {
+        if k == 0 {
+            return vec![Vec::new()];
+        }
+        let mut out = Vec::new();
+        for (i, first) in items.iter().enumerate() {
+            for mut rest in choose(&items[i + 1..], k - 1) {
+                rest.insert(0, first);
+                out.push(rest);
+            }
+        }
+        out
+    }
+
+    fn owned(shares: &[&Share]) -> Vec<Share> {
+        shares.iter().map(|s| Share::decode(&s.encode(ShareEncoding::Text)).unwrap()).collect()
+    }
+
+    #[test]
+    fn any_threshold_of_shares_recover_the_secret() {
+        for n in 2..=6u8 {
+            for k in 2..=n {
+                let shares = split(b"correct horse", SecretKind::Text, k, n).unwrap();
+                for subset in choose(&shares, k as usize) {
+                    assert_eq!(combine(&owned(&subset)).unwrap(), (SecretKind::Text, b"correct horse".to_vec()));
+                }
+                for subset in choose(&shares, k as usize - 1) {
+                    assert!(matches!(combine(&owned(&subset)), Err(Error::InvalidInput(_))));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn too_few_shares_claiming_a_lower_threshold_fail_the_check() {
+        let shares = split(&KEY, SecretKind::Key, 3, 5).unwrap();
+        let forged = shares[..2].iter().map(|s| Share { threshold: 2, ..owned(&[s]).remove(0) }).collect::<Vec<_>>();
+        assert!(matches!(combine(&forged), Err(Error::Corrupted(_))));
+    }
+
+    #[test]
+    fn shares_from_different_splits_do_not_mix() {
+        let a = split(&KEY, SecretKind::Key, 2, 3).unwrap();
+        let mut b = split(&KEY, SecretKind::Key, 2, 3).unwrap();
+        b[1].set_id = [a[0].set_id[0], a[0].set_id[1] ^ 1];
+        assert!(combine(&[owned(&[&a[0]]).remove(0), b.remove(1)]).is_err());
+    }
+
+    #[test]
+    fn every_encoding_decodes() {
+        // Many splits, so set IDs and data cover Base64 that is mostly hex digits
+        for len in 1..=40 {
+            let secret = vec![0xa5; len];
+            for share in split(&secret, SecretKind::Text, 2, 3).unwrap() {
+                for encoding in ShareEncoding::value_variants() {
+                    let decoded = Share::decode(&share.encode(*encoding)).unwrap();
+                    assert_eq!(decoded.to_bytes(), share.to_bytes(), "{:?}", encoding);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn a_mistyped_share_is_rejected() {
+        let share = split(&KEY, SecretKind::Key, 2, 2).unwrap().remove(0).encode(ShareEncoding::Hex);
+        let typo = format!("{}{}", &share[..share.len() - 1], if share.ends_with('0') { '1' } else { '0' });
+        assert!(matches!(Share::decode(&typo), Err(Error::Corrupted(_))));
+    }
+
+    #[test]
+    fn classifies_base64_keys_as_keys() {
+        assert_eq!(classify(&base64::encode(KEY)), (SecretKind::Key, KEY.to_vec()));
+        assert_eq!(classify(&keyfile::to_file_string(&KEY, None, 0).unwrap()), (SecretKind::Key, KEY.to_vec()));
+    }
+
+    #[test]
+    fn keeps_recovery_phrases_as_text() {
+        let phrase = mnemonic::encode_key(&KEY).unwrap();
+        assert_eq!(classify(&phrase), (SecretKind::Text, phrase.as_bytes().to_vec()));
+        assert_eq!(classify("hunter2"), (SecretKind::Text, b"hunter2".to_vec()));
+    }
+}
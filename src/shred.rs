@@ -0,0 +1,187 @@
+// shred.rs
+
+use rand::Rng;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::archive;
+use crate::container;
+use crate::error::{Error, Result};
+use crate::output;
+
+/// Limits of overwriting in place, shown whenever files are shredded
+pub const CAVEAT: &str = "Overwriting can't reach copies kept by SSD wear levelling, copy-on-write filesystems \
+(btrfs, ZFS, APFS), snapshots or backups. Full-disk encryption is the reliable protection there.";
+
+// Overwrites the first `len` bytes of a file with random data, or zeros, and flushes it to disk
+fn overwrite(file: &mut File, len: u64, random: bool) -> io::Result<()> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut rng = rand::thread_rng();
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(buf.len() as u64) as usize;
+        if random {
+            rng.fill(&mut buf[..n]);
+        }
+        file.write_all(&buf[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()
+}
+
+/// Overwrites a regular file with random data, then zeros, and removes it
+pub fn shred_file(path: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if !metadata.is_file() {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "Not a regular file"));
+    }
+
+    let mut file = OpenOptions::new().write(true).open(path)?;
+    overwrite(&mut file, metadata.len(), true)?;
+    overwrite(&mut file, metadata.len(), false)?;
+    drop(file);
+
+    // Rename first so the original name doesn't linger in the directory entry
+    let renamed = output::staging_path(path, "shred");
+    fs::rename(path, &renamed)?;
+    fs::remove_file(&renamed)
+}
+
+/// Shreds a file, or every file beneath a directory and then the directory itself.
+/// Symlinks are removed without touching what they point to. Returns the number of files shredded.
+pub fn shred_path(path: &Path) -> io::Result<usize> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_dir() {
+        let mut count = 0;
+        for entry in fs::read_dir(path)? {
+            count += shred_path(&entry?.path())?;
+        }
+        fs::remove_dir(path)?;
+        Ok(count)
+    } else if metadata.is_file() {
+        shred_file(path).map(|_| 1)
+    } else {
+        fs::remove_file(path).map(|_| 0)
+    }
+}
+
+/// Shreds the source of an encryption, but only once the encrypted output on disk
+/// has been read back and shown to decrypt to exactly `plaintext`, and the source
+/// itself still reads as `plaintext`: a file edited or a directory that gained files
+/// since it was read would otherwise lose what the encrypted file doesn't have.
+pub fn shred_source(source: &Path, encrypted: &Path, plaintext: &[u8], key: &[u8]) -> Result<usize> {
+    let source_abs = source.canonicalize().map_err(|e| Error::io(format!("Failed to resolve {}", source.display()), e))?;
+    let encrypted_abs = encrypted.canonicalize().map_err(|e| Error::io(format!("Failed to resolve {}", encrypted.display()), e))?;
+    if encrypted_abs.starts_with(&source_abs) {
+        return Err(Error::InvalidInput("The encrypted output is inside the source, refusing to shred it.".into()));
+    }
+
+    let data = fs::read(encrypted).map_err(|e| Error::io(format!("Failed to read back {}", encrypted.display()), e))?;
+    let decrypted = container::open(&data, key).map_err(|e| Error::Corrupted(format!("Encrypted output failed to decrypt: {}", e)))?;
+    if decrypted.plaintext != plaintext {
+        return Err(Error::Corrupted("Encrypted output doesn't match the original, refusing to shred it.".into()));
+    }
+
+    // Read the same way the encryption read it, archives included
+    let current = if source.is_dir() { archive::pack_dir(source) } else { fs::read(source) }
+        .map_err(|e| Error::io(format!("Failed to read {} again", source.display()), e))?;
+    if current != plaintext {
+        return Err(Error::InvalidInput(format!(
+            "{} changed after it was encrypted, refusing to shred it. Encrypt it again to include the changes.",
+            source.display()
+        )));
+    }
+
+    shred_path(source).map_err(|e| Error::io(format!("Failed to shred {}", source.display()), e))
+}
+
+/// Everything directly inside a directory, e.g. the leftovers in data/decrypted
+pub fn entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::{FileInfo, PayloadKind};
+    use crate::testing::TempDir;
+
+    // Encrypts `source` the way the app does, returning the output path and what was sealed
+    fn encrypt(source: &Path, out_dir: &Path, key: &[u8]) -> (PathBuf, Vec<u8>) {
+        let (kind, plaintext) = if source.is_dir() {
+            (PayloadKind::Archive, archive::pack_dir(source).unwrap())
+        } else {
+            (PayloadKind::File, fs::read(source).unwrap())
+        };
+        let sealed = container::seal(&plaintext, kind, &FileInfo::read(source).unwrap(), key).unwrap();
+        let out = out_dir.join("source.locked");
+        fs::write(&out, sealed).unwrap();
+        (out, plaintext)
+    }
+
+    #[test]
+    fn shreds_an_unchanged_file() {
+        let tmp = TempDir::new("shred-unchanged");
+        let source = tmp.path().join("notes.txt");
+        fs::write(&source, "secret notes").unwrap();
+        let (out, plaintext) = encrypt(&source, tmp.path(), &[7; 32]);
+
+        assert_eq!(shred_source(&source, &out, &plaintext, &[7; 32]).unwrap(), 1);
+        assert!(!source.exists());
+    }
+
+    #[test]
+    fn shreds_an_unchanged_directory() {
+        let tmp = TempDir::new("shred-unchanged-dir");
+        let source = tmp.path().join("docs");
+        fs::create_dir_all(source.join("inner")).unwrap();
+        fs::write(source.join("a.txt"), "a").unwrap();
+        fs::write(source.join("inner").join("b.txt"), "b").unwrap();
+        let (out, plaintext) = encrypt(&source, tmp.path(), &[7; 32]);
+
+        assert_eq!(shred_source(&source, &out, &plaintext, &[7; 32]).unwrap(), 2);
+        assert!(!source.exists());
+    }
+
+    #[test]
+    fn keeps_a_file_edited_since() {
+        let tmp = TempDir::new("shred-edited");
+        let source = tmp.path().join("notes.txt");
+        fs::write(&source, "secret notes").unwrap();
+        let (out, plaintext) = encrypt(&source, tmp.path(), &[7; 32]);
+        fs::write(&source, "secret notes, and more").unwrap();
+
+        assert!(matches!(shred_source(&source, &out, &plaintext, &[7; 32]), Err(Error::InvalidInput(_))));
+        assert_eq!(fs::read_to_string(&source).unwrap(), "secret notes, and more");
+    }
+
+    #[test]
+    fn keeps_a_directory_that_gained_files() {
+        let tmp = TempDir::new("shred-gained");
+        let source = tmp.path().join("docs");
+        fs::create_dir(&source).unwrap();
+        fs::write(source.join("a.txt"), "a").unwrap();
+        let (out, plaintext) = encrypt(&source, tmp.path(), &[7; 32]);
+        fs::write(source.join("b.txt"), "b").unwrap();
+
+        assert!(shred_source(&source, &out, &plaintext, &[7; 32]).is_err());
+        assert!(source.join("a.txt").exists() && source.join("b.txt").exists());
+    }
+
+    #[test]
+    fn keeps_the_source_when_the_key_is_wrong() {
+        let tmp = TempDir::new("shred-wrong-key");
+        let source = tmp.path().join("notes.txt");
+        fs::write(&source, "secret notes").unwrap();
+        let (out, plaintext) = encrypt(&source, tmp.path(), &[7; 32]);
+
+        assert!(matches!(shred_source(&source, &out, &plaintext, &[8; 32]), Err(Error::Corrupted(_))));
+        assert!(source.exists());
+    }
+}
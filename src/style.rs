@@ -0,0 +1,215 @@
+// style.rs
+
+//! How the interface looks. Messages are put together from these helpers: `paint` colours
+//! a piece when the stream has colour, `text` is for the app's own wording, whose emoji
+//! become words when emoji are off. Values put into a message (keys, paths, secrets)
+//! never go through `text`, so they are always shown as they are.
+
+use std::borrow::Cow;
+use std::env;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use key_gen_app::config::ColorChoice;
+
+static COLOR: AtomicBool = AtomicBool::new(true);
+static ERR_COLOR: AtomicBool = AtomicBool::new(true);
+static EMOJI: AtomicBool = AtomicBool::new(true);
+
+// What emoji with a meaning become when emoji are off. Others are left out.
+const EMOJI_WORDS: [(&str, &str); 5] = [("✅", "OK:"), ("❌", "Error:"), ("⚠\u{fe0f}", "Warning:"), ("⚠", "Warning:"), ("📋", "Clipboard:")];
+
+/// Ends whatever colour came before it
+pub const RESET: &str = "\x1b[0m";
+
+/// What a piece of the interface is, which decides its colour
+#[derive(Clone, Copy)]
+pub enum Tone {
+    /// Things that worked, and headings
+    Success,
+    /// Things that failed
+    Error,
+    /// Warnings, notes and cancellations
+    Warning,
+    /// Keys, paths and answers
+    Value,
+    /// Checksums and key IDs
+    Id,
+    /// Hints and help
+    Muted,
+    /// Directories in the file browser
+    Dir,
+    /// The selected row of a list
+    Selected,
+    /// The preset a key was generated with
+    Preset,
+}
+
+impl Tone {
+    fn sgr(self) -> &'static str {
+        match self {
+            Tone::Success => "92",
+            Tone::Error => "91",
+            Tone::Warning => "93",
+            Tone::Value => "96",
+            Tone::Id => "95",
+            Tone::Muted => "90",
+            Tone::Dir => "94",
+            Tone::Selected => "7",
+            Tone::Preset => "38;5;198",
+        }
+    }
+}
+
+/// Decides how output looks for the rest of the run. `auto` colours stdout and stderr
+/// each only when it is a terminal, and not at all when NO_COLOR is set or TERM is dumb.
+pub fn setup(color: ColorChoice, emoji: bool) {
+    let allowed = env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && !dumb_terminal();
+    let (out, err) = match color {
+        ColorChoice::Always => (true, true),
+        ColorChoice::Never => (false, false),
+        ColorChoice::Auto => (allowed && io::stdout().is_terminal(), allowed && io::stderr().is_terminal()),
+    };
+    COLOR.store(out, Ordering::Relaxed);
+    ERR_COLOR.store(err, Ordering::Relaxed);
+    EMOJI.store(emoji, Ordering::Relaxed);
+}
+
+/// Whether TERM says the terminal can't move the cursor or show colour
+pub fn dumb_terminal() -> bool {
+    env::var("TERM").is_ok_and(|term| term == "dumb")
+}
+
+/// `text` in the colour of `tone`, when stdout has colour
+pub fn paint(tone: Tone, text: impl fmt::Display) -> String {
+    colored(COLOR.load(Ordering::Relaxed), tone.sgr(), text)
+}
+
+/// `paint` for a line going to stderr
+pub fn paint_err(tone: Tone, text: impl fmt::Display) -> String {
+    colored(ERR_COLOR.load(Ordering::Relaxed), tone.sgr(), text)
+}
+
+/// `text` in the colour given by SGR parameters like "96", when stdout has colour
+pub fn color(sgr: &str, text: &str) -> String {
+    colored(COLOR.load(Ordering::Relaxed), sgr, text)
+}
+
+fn colored(on: bool, sgr: &str, text: impl fmt::Display) -> String {
+    if on {
+        format!("\x1b[{}m{}{}", sgr, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+/// The app's own wording, like a screen title or "✅". When emoji are off, the ones with a
+/// meaning become words ("OK:") and the rest are left out.
+pub fn text(text: &str) -> Cow<'_, str> {
+    if EMOJI.load(Ordering::Relaxed) || text.is_ascii() {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(without_emoji(text))
+    }
+}
+
+/// The arrow in front of a result line, or a dash when emoji are off
+pub fn bullet() -> &'static str {
+    if EMOJI.load(Ordering::Relaxed) { "→" } else { "-" }
+}
+
+// Swaps emoji for words, or drops them with the spaces after them
+fn without_emoji(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some((emoji, word)) = EMOJI_WORDS.iter().find(|(emoji, _)| rest.starts_with(emoji)) {
+            out.push_str(word);
+            rest = &rest[emoji.len()..];
+            // One space after the word, however many the emoji had
+            if rest.starts_with(' ') {
+                out.push(' ');
+                rest = rest.trim_start_matches(' ');
+            }
+        } else if is_emoji(c) {
+            rest = rest[c.len_utf8()..].trim_start_matches(['\u{fe0f}', ' ']);
+        } else {
+            out.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    out
+}
+
+/// `line` without its terminal escape sequences (colours, cursor moves), e.g. to measure it
+pub fn strip_color(line: &str) -> Cow<'_, str> {
+    if !line.contains('\x1b') {
+        return Cow::Borrowed(line);
+    }
+
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find("\x1b[") {
+        out.push_str(&rest[..start]);
+        // Parameter and intermediate bytes, then one final byte
+        let after = &rest[start + 2..];
+        let end = after.find(|c: char| !matches!(c, '\x20'..='\x3f')).unwrap_or(after.len());
+        rest = match after[end..].chars().next() {
+            Some('\x40'..='\x7e') => &after[end + 1..],
+            _ => &after[end..],
+        };
+    }
+    out.push_str(rest);
+    Cow::Owned(out)
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c as u32, 0x1F300..=0x1FAFF | 0x2600..=0x27BF | 0xFE0F | 0x200D)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_escape_sequences() {
+        assert_eq!(strip_color("plain"), "plain");
+        assert_eq!(strip_color("\x1b[92mOK\x1b[0m done"), "OK done");
+        assert_eq!(strip_color("\x1b[38;5;198m(key)\x1b[0m"), "(key)");
+        assert_eq!(strip_color("\x1b[7m▸ row\x1b[m"), "▸ row");
+        // Not only colours: cursor moves and clears are CSI sequences too
+        assert_eq!(strip_color("a\x1b[2Kb\x1b[10;20Hc"), "abc");
+        // An escape that doesn't start a sequence is kept, as is one cut off at the end
+        assert_eq!(strip_color("a\x1bb"), "a\x1bb");
+        assert_eq!(strip_color("cut \x1b[12"), "cut ");
+    }
+
+    #[test]
+    fn swaps_emoji_for_words() {
+        assert_eq!(without_emoji("✅ Encryption Complete"), "OK: Encryption Complete");
+        assert_eq!(without_emoji("❌ Tag does not match."), "Error: Tag does not match.");
+        assert_eq!(without_emoji("⚠️  x already exists."), "Warning: x already exists.");
+        assert_eq!(without_emoji("⚠ bare"), "Warning: bare");
+        assert_eq!(without_emoji("📋"), "Clipboard:");
+    }
+
+    #[test]
+    fn drops_other_emoji_with_their_spaces() {
+        assert_eq!(without_emoji("📦 Encrypt"), "Encrypt");
+        assert_eq!(without_emoji("🔑 crypto-bro"), "crypto-bro");
+        // Sequences joined with ZWJ, skin tones and variation selectors go as a whole
+        assert_eq!(without_emoji("👨\u{200d}👩\u{200d}👧 family"), "family");
+        assert_eq!(without_emoji("👍🏽 fine"), "fine");
+        assert_eq!(without_emoji("☀\u{fe0f} sun"), "sun");
+        // Other symbols are text, not emoji
+        assert_eq!(without_emoji("[←] up · ▸ row ‹ 16 › …"), "[←] up · ▸ row ‹ 16 › …");
+    }
+
+    #[test]
+    fn values_are_painted_as_they_are() {
+        let value = "notes✅\x1b[31m.txt";
+        assert_eq!(colored(false, "92", value), value);
+        assert_eq!(colored(true, "92", value), format!("\x1b[92m{}{}", value, RESET));
+    }
+}
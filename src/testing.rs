@@ -0,0 +1,43 @@
+// testing.rs
+
+//! Fixtures shared by the unit tests
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A fresh directory for one test under the system temp directory, removed with it.
+/// The process ID in its name keeps parallel test runs apart.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` has to be unique across the crate's tests, e.g. "shred-edited"
+    pub fn new(name: &str) -> TempDir {
+        let dir = std::env::temp_dir().join(format!("crypto-bro-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        TempDir(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Writes a file in the directory and returns its path
+    pub fn file(&self, name: &str, contents: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Passphrase prompt for keys that aren't encrypted, failing the test if it's asked
+pub fn no_passphrase() -> io::Result<String> {
+    panic!("a passphrase was asked for a key that isn't encrypted")
+}
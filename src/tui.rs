@@ -0,0 +1,1223 @@
+// tui.rs
+
+//! The full-screen interface behind the menus. Each screen keeps a transcript of
+//! what it has shown, asks its questions below it, and a history of this
+//! session's keys and results sits on the right.
+//! The terminal is put back the way it was on every way out: quitting, errors,
+//! panics and Ctrl-C (which raw mode turns into a key press).
+//! In plain mode the same questions are asked line by line instead, for screen
+//! readers, logs and terminals that can't draw a full screen.
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{self, BeginSynchronizedUpdate, Clear, ClearType, EndSynchronizedUpdate, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+use key_gen_app::{clipboard, Error};
+
+use crate::prompt;
+use crate::style::{self, paint, Tone};
+
+/// Adds a formatted line to the current screen, like `println!`
+macro_rules! say {
+    ($ui:expr) => { $ui.say("") };
+    ($ui:expr, $($arg:tt)*) => { $ui.say(format!($($arg)*)) };
+}
+pub(crate) use say;
+
+const HISTORY_WIDTH: usize = 34;
+// Narrower terminals leave the history pane out
+const MIN_WIDTH_FOR_HISTORY: usize = 90;
+const HISTORY_LIMIT: usize = 50;
+// How often the clipboard countdown is refreshed while waiting for a key
+const TICK: Duration = Duration::from_millis(200);
+// Entries listed by the plain mode browser before it asks for a path instead
+const PLAIN_LIST_LIMIT: usize = 40;
+const FILTER_PROMPT: &str = "Show only files ending in (e.g. txt, locked; empty shows all):";
+// Recursive listings stop going deeper once they have this many files
+const WALK_LIMIT: usize = 5000;
+
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Leaves the full screen and raw mode, if the interface is running. Safe to call more than once.
+pub fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        let _ = execute!(io::stdout(), EndSynchronizedUpdate, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Where a screen goes when it's done
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Next {
+    Menu,
+    Quit,
+}
+
+/// A menu entry, chosen with its key or by moving to it and pressing Enter
+pub struct Item {
+    pub key: char,
+    pub label: String,
+    pub detail: String,
+}
+
+impl Item {
+    pub fn new(key: char, label: impl Into<String>) -> Item {
+        Item { key, label: label.into(), detail: String::new() }
+    }
+
+    /// Dimmed text after the label
+    pub fn detail(mut self, detail: impl Into<String>) -> Item {
+        self.detail = detail.into();
+        self
+    }
+}
+
+/// What the file browser picks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pick {
+    File,
+    Dir,
+}
+
+/// A setting in a form
+pub struct Field {
+    pub label: &'static str,
+    pub help: &'static str,
+    pub value: Value,
+}
+
+pub enum Value {
+    Number { value: usize, min: usize, max: usize },
+    Toggle(bool),
+    Choice { options: &'static [&'static str], index: usize },
+}
+
+impl Value {
+    fn show(&self) -> String {
+        match self {
+            Value::Number { value, .. } => value.to_string(),
+            Value::Toggle(on) => if *on { "yes" } else { "no" }.into(),
+            Value::Choice { options, index } => options[*index].into(),
+        }
+    }
+
+    // Left and right arrows
+    fn step(&mut self, up: bool) {
+        match self {
+            Value::Number { value, min, max } => {
+                *value = if up { (*value + 1).min(*max) } else { value.saturating_sub(1).max(*min) };
+            }
+            Value::Toggle(on) => *on = !*on,
+            Value::Choice { options, index } => {
+                *index = if up { (*index + 1) % options.len() } else { (*index + options.len() - 1) % options.len() };
+            }
+        }
+    }
+
+    // A typed answer, in plain mode
+    fn set(&mut self, text: &str) -> Result<(), String> {
+        match self {
+            Value::Number { value, min, max } => match text.parse::<usize>() {
+                Ok(number) if (*min..=*max).contains(&number) => *value = number,
+                _ => return Err(format!("Type a number from {} to {}.", min, max)),
+            },
+            Value::Toggle(on) => match text.to_lowercase().as_str() {
+                "y" | "yes" => *on = true,
+                "n" | "no" => *on = false,
+                _ => return Err("Type yes or no.".into()),
+            },
+            Value::Choice { options, index } => match options.iter().position(|o| o.eq_ignore_ascii_case(text)) {
+                Some(position) => *index = position,
+                None => return Err(format!("Type one of: {}.", options.join(", "))),
+            },
+        }
+        Ok(())
+    }
+}
+
+// Something this session produced, shown in the history pane
+struct Entry {
+    time: String,
+    label: String,
+    // What Enter copies, e.g. the generated key
+    value: Option<String>,
+}
+
+// A line of the file browser
+struct Row {
+    path: PathBuf,
+    name: String,
+    is_dir: bool,
+    size: Option<u64>,
+}
+
+impl Row {
+    fn label(&self) -> String {
+        match (self.is_dir, self.size) {
+            (true, _) => paint(Tone::Dir, format_args!("{}/", self.name)),
+            (false, Some(size)) => format!("{}  {}", self.name, paint(Tone::Muted, size_label(size))),
+            (false, None) => self.name.clone(),
+        }
+    }
+}
+
+// What the file browser lists, toggled from its keys
+struct View {
+    pick: Pick,
+    show_hidden: bool,
+    // Every file below the directory instead of just its entries
+    recursive: bool,
+    // Lower case, without the dot. Empty shows all files.
+    extensions: Vec<String>,
+}
+
+// How `edit` treats what is typed
+#[derive(Clone, Copy)]
+enum Input<'a> {
+    Text,
+    Hidden,
+    // Tab completes against the files in the directory, relative ones starting from this one
+    Path(&'a Path),
+}
+
+/// The running interface. Dropping it restores the terminal.
+pub struct Ui {
+    title: String,
+    lines: Vec<String>,
+    // Lines scrolled back from the end of the screen
+    scroll: usize,
+    history: Vec<Entry>,
+    clipboard: clipboard::Settings,
+    // Whether the last copy is due to be cleared, so the footer can say when it was
+    clear_pending: bool,
+    // After Ctrl-C every question fails, so the whole session unwinds
+    interrupted: bool,
+    // Line by line instead of the full screen
+    plain: bool,
+}
+
+impl Ui {
+    /// Switches to the full screen, or with `plain` starts asking line by line.
+    /// A panic from here on restores the terminal before its message is printed.
+    pub fn start(clipboard: clipboard::Settings, plain: bool) -> io::Result<Ui> {
+        let ui = Ui {
+            title: String::new(),
+            lines: Vec::new(),
+            scroll: 0,
+            history: Vec::new(),
+            clipboard,
+            clear_pending: false,
+            interrupted: false,
+            plain,
+        };
+        if plain {
+            return Ok(ui);
+        }
+
+        let default_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore();
+            default_hook(info);
+        }));
+
+        terminal::enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        if let Err(e) = execute!(io::stdout(), EnterAlternateScreen, Hide) {
+            restore();
+            return Err(e);
+        }
+        Ok(ui)
+    }
+
+    /// Whether questions are asked line by line
+    pub fn is_plain(&self) -> bool {
+        self.plain
+    }
+
+    /// Starts a new, empty screen
+    pub fn screen(&mut self, title: &str) {
+        self.title = style::text(title).into_owned();
+        if self.plain {
+            println!("\n{}", paint(Tone::Success, &self.title));
+        }
+        self.lines.clear();
+        self.scroll = 0;
+    }
+
+    /// Adds text to the current screen, one line per line of `text`
+    pub fn say(&mut self, text: impl Into<String>) {
+        let text = text.into();
+        if self.plain {
+            println!("{}", text);
+            return;
+        }
+        self.lines.extend(text.split('\n').map(str::to_string));
+        self.scroll = 0;
+    }
+
+    /// Adds a result to the history pane. Entries with a value can be copied again from there.
+    pub fn record(&mut self, label: impl Into<String>, value: Option<String>) {
+        let now = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+        let time = now.get(11..19).unwrap_or_default().to_string();
+        self.history.insert(0, Entry { time, label: label.into(), value });
+        self.history.truncate(HISTORY_LIMIT);
+    }
+
+    /// Whether Ctrl-C was pressed
+    pub fn interrupted(&self) -> bool {
+        self.interrupted
+    }
+
+    /// Rows between the header and the footer
+    pub fn body_height(&self) -> usize {
+        terminal::size().map(|(_, h)| h as usize).unwrap_or(24).saturating_sub(4)
+    }
+
+    /// Columns a line of the screen's text can take before it wraps
+    pub fn body_width(&self) -> usize {
+        let width = terminal::size().map(|(w, _)| w as usize).unwrap_or(80);
+        let history = if width >= MIN_WIDTH_FOR_HISTORY { HISTORY_WIDTH + 1 } else { 0 };
+        width.saturating_sub(history + 2)
+    }
+
+    /// Copies text to the clipboard and says how it went
+    pub fn copy(&mut self, text: &str) {
+        match clipboard::copy(text, &self.clipboard) {
+            Ok(copied) => {
+                say!(self, "{} (via {})", paint(Tone::Success, "Key copied to clipboard successfully!"), copied.backend);
+                if let Some(after) = self.clipboard.clear_after {
+                    say!(self, "It will be cleared in {}s, unless you copy something else first.", after.as_secs());
+                }
+                if self.clipboard.exclude_from_history && !copied.hidden_from_history {
+                    say!(self, "{}", paint(Tone::Warning, format_args!("{} can't ask clipboard managers to skip the copy, so one may keep it. `--clipboard native` can.", copied.backend)));
+                }
+                self.clear_pending = self.clipboard.clear_after.is_some();
+            }
+            Err(e) => say!(self, "{}", paint(Tone::Error, e)),
+        }
+    }
+
+    /// Shows a menu below the screen's text and returns the key of the chosen item, or `None` for Esc.
+    /// `selected` is highlighted first. Tab moves to the history pane, where Enter copies an entry.
+    pub fn choose(&mut self, prompt: &str, items: &[Item], selected: char) -> Result<Option<char>, Error> {
+        if self.plain {
+            return self.choose_line(prompt, items, selected);
+        }
+        let mut index = items.iter().position(|i| i.key == selected).unwrap_or(0);
+        let mut in_history: Option<usize> = None;
+        let label_width = items.iter().map(|i| columns(&i.label)).max().unwrap_or(0) + 2;
+
+        loop {
+            let mut panel = Vec::new();
+            if !prompt.is_empty() {
+                panel.push(prompt.to_string());
+            }
+            for (i, item) in items.iter().enumerate() {
+                let padding = " ".repeat(label_width - columns(&item.label));
+                panel.push(if i == index && in_history.is_none() {
+                    paint(Tone::Selected, format_args!("▸ [{}] {}{}{}", item.key, item.label, padding, item.detail))
+                } else {
+                    format!("  [{}] {}{}{}", item.key, item.label, padding, paint(Tone::Muted, &item.detail))
+                });
+            }
+            let hints = match in_history {
+                Some(_) => "↑↓ move · Enter copy · Tab back to the menu".to_string(),
+                None if self.history_visible() => "↑↓ move · Enter or key select · Esc back · Tab history · PgUp/PgDn scroll".to_string(),
+                None => "↑↓ move · Enter or key select · Esc back · PgUp/PgDn scroll".to_string(),
+            };
+
+            let key = self.key(&panel, &hints, in_history)?;
+            if let Some(entry) = in_history {
+                match key.code {
+                    KeyCode::Up => in_history = Some(entry.saturating_sub(1)),
+                    KeyCode::Down => in_history = Some((entry + 1).min(self.history.len() - 1)),
+                    KeyCode::Enter => {
+                        if let Some(value) = self.history[entry].value.clone() {
+                            self.copy(&value);
+                        }
+                    }
+                    KeyCode::Tab | KeyCode::Esc => in_history = None,
+                    _ => {}
+                }
+                continue;
+            }
+
+            let chosen = match key.code {
+                KeyCode::Up => {
+                    index = (index + items.len() - 1) % items.len();
+                    continue;
+                }
+                KeyCode::Down => {
+                    index = (index + 1) % items.len();
+                    continue;
+                }
+                KeyCode::Home => {
+                    index = 0;
+                    continue;
+                }
+                KeyCode::End => {
+                    index = items.len() - 1;
+                    continue;
+                }
+                KeyCode::Tab if self.history_visible() => {
+                    in_history = Some(0);
+                    continue;
+                }
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Enter => &items[index],
+                KeyCode::Char(c) => match items.iter().find(|i| i.key == c) {
+                    Some(item) => item,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            if !prompt.is_empty() {
+                say!(self, "{} {}", prompt, paint(Tone::Value, &chosen.label));
+            }
+            return Ok(Some(chosen.key));
+        }
+    }
+
+    /// Asks a yes/no question. Anything but y is no.
+    pub fn confirm(&mut self, question: &str) -> Result<bool, Error> {
+        if self.plain {
+            let answer = self.ask_line(&format!("{} (y/N): ", question))?;
+            return Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"));
+        }
+        let panel = [format!("{} (y/N)", question)];
+        loop {
+            let yes = match self.key(&panel, "y yes · n, Enter or Esc no", None)?.code {
+                KeyCode::Char('y' | 'Y') => true,
+                KeyCode::Char('n' | 'N') | KeyCode::Enter | KeyCode::Esc => false,
+                _ => continue,
+            };
+            say!(self, "{} {}", question, paint(Tone::Value, if yes { "yes" } else { "no" }));
+            return Ok(yes);
+        }
+    }
+
+    /// Reads a line of text, or `None` if Esc was pressed
+    pub fn input(&mut self, prompt: &str) -> Result<Option<String>, Error> {
+        self.edit(prompt, Input::Text)
+    }
+
+    /// Like `input`, without showing what is typed
+    pub fn input_hidden(&mut self, prompt: &str) -> Result<Option<String>, Error> {
+        self.edit(prompt, Input::Hidden)
+    }
+
+    /// Reads a path, with Tab completion and `~` for the home directory. Relative paths start
+    /// from `base`. `None` if Esc was pressed or nothing was typed.
+    pub fn input_path(&mut self, prompt: &str, base: &Path) -> Result<Option<PathBuf>, Error> {
+        let text = self.edit(prompt, Input::Path(base))?;
+        Ok(text.filter(|t| !t.trim().is_empty()).map(|t| base.join(expand_home(t.trim()))))
+    }
+
+    fn edit(&mut self, prompt: &str, input: Input) -> Result<Option<String>, Error> {
+        let hidden = matches!(input, Input::Hidden);
+        if self.plain {
+            return self.edit_line(prompt, hidden).map(Some);
+        }
+        let mut text: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // What else Tab could have completed to
+        let mut candidates: Vec<String> = Vec::new();
+        loop {
+            let shown = if hidden {
+                paint(Tone::Muted, "(typing is hidden)")
+            } else {
+                let before = text[..cursor].iter().collect::<String>();
+                let at = text.get(cursor).copied().unwrap_or(' ');
+                let after = text.get(cursor + 1..).unwrap_or_default().iter().collect::<String>();
+                format!("{}{}{}", before, paint(Tone::Selected, at), after)
+            };
+            let mut panel = vec![prompt.to_string(), format!("{} {}", paint(Tone::Value, "›"), shown)];
+            if !candidates.is_empty() {
+                panel.push(format!("  {}", paint(Tone::Muted, candidates.join("  "))));
+            }
+            let hints = match input {
+                Input::Path(_) => "Tab complete · Enter accept · Esc cancel · Ctrl-U clear",
+                _ => "Enter accept · Esc cancel · Ctrl-U clear",
+            };
+
+            let key = self.key(&panel, hints, None)?;
+            let control = key.modifiers.contains(KeyModifiers::CONTROL);
+            candidates.clear();
+            match key.code {
+                KeyCode::Tab => {
+                    if let Input::Path(base) = input {
+                        let (completed, others) = complete_path(&text.iter().collect::<String>(), base);
+                        text = completed.chars().collect();
+                        cursor = text.len();
+                        candidates = others;
+                    }
+                }
+                KeyCode::Enter => {
+                    let text = text.iter().collect::<String>();
+                    say!(self, "{} {}", prompt, paint(Tone::Value, if hidden && !text.is_empty() { "(hidden)" } else { &text }));
+                    return Ok(Some(text));
+                }
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('u') if control => {
+                    text.clear();
+                    cursor = 0;
+                }
+                KeyCode::Char(c) if !control => {
+                    text.insert(cursor, c);
+                    cursor += 1;
+                }
+                KeyCode::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    text.remove(cursor);
+                }
+                KeyCode::Delete if cursor < text.len() => {
+                    text.remove(cursor);
+                }
+                KeyCode::Left => cursor = cursor.saturating_sub(1),
+                KeyCode::Right => cursor = (cursor + 1).min(text.len()),
+                KeyCode::Home => cursor = 0,
+                KeyCode::End => cursor = text.len(),
+                _ => {}
+            }
+        }
+    }
+
+    /// Asks for a new passphrase twice, until both match. `None` if Esc was pressed.
+    pub fn new_passphrase(&mut self, prompt: &str) -> Result<Option<String>, Error> {
+        loop {
+            let Some(pass) = self.input_hidden(prompt)? else {
+                return Ok(None);
+            };
+            let Some(confirm) = self.input_hidden("Confirm passphrase:")? else {
+                return Ok(None);
+            };
+            if pass == confirm {
+                return Ok(Some(pass));
+            }
+            say!(self, "{}", paint(Tone::Error, "Passphrases do not match, try again."));
+        }
+    }
+
+    /// Reads a passphrase for library calls that ask through a callback, e.g. `keyfile::decode`
+    pub fn read_passphrase(&mut self, prompt: &str) -> io::Result<String> {
+        self.input_hidden(prompt)
+            .map_err(into_io)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Interrupted, "Cancelled."))
+    }
+
+    /// Asks what to do about an output that already exists, for `output::resolve`.
+    /// Returns where to write, or `None` to cancel.
+    pub fn ask_collision(&mut self, path: &Path, alternative: PathBuf) -> io::Result<Option<PathBuf>> {
+        say!(self, "{}", paint(Tone::Warning, format_args!("{}{} already exists.", style::text("⚠️  "), path.display())));
+        let items = [
+            Item::new('o', "Overwrite it"),
+            Item::new('r', format!("Keep both (save as {})", alternative.display())),
+            Item::new('c', "Cancel"),
+        ];
+        match self.choose("", &items, 'c').map_err(into_io)? {
+            Some('o') => Ok(Some(path.to_path_buf())),
+            Some('r') => Ok(Some(alternative)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Lets the user pick a file or directory, starting in `start`. Only entries `include`
+    /// accepts can be picked, and with `extensions` only files ending in one of them are shown
+    /// at first. A path can also be typed, and the listing can be filtered or made recursive.
+    pub fn browse(&mut self, prompt: &str, start: &Path, pick: Pick, extensions: &[&str], include: impl Fn(&Path) -> bool) -> Result<Option<PathBuf>, Error> {
+        let mut dir = start_dir(start)?;
+        let mut view = View { pick, show_hidden: false, recursive: false, extensions: extensions.iter().map(|e| e.to_lowercase()).collect() };
+        if self.plain {
+            return self.browse_line(prompt, dir, view, include);
+        }
+        let mut listing = list_dir(&dir, &view, &include);
+        let mut index = first_entry(&listing);
+
+        loop {
+            let mut panel = vec![prompt.to_string(), heading(&dir, &view)];
+            match &listing {
+                Ok(rows) => {
+                    let rows_shown = self.body_height().saturating_sub(5).clamp(3, 20);
+                    let first = index.saturating_sub(rows_shown - 1).min(rows.len().saturating_sub(rows_shown));
+                    for (i, row) in rows.iter().enumerate().skip(first).take(rows_shown) {
+                        let line = row.label();
+                        panel.push(if i == index { paint(Tone::Selected, format_args!("▸ {}", style::strip_color(&line))) } else { format!("  {}", line) });
+                    }
+                    if rows.iter().all(|r| r.name == ".." || r.name == ".") {
+                        panel.push(format!("  {}", paint(Tone::Muted, "(nothing here)")));
+                    }
+                }
+                Err(e) => panel.push(format!("  {}", paint(Tone::Error, e))),
+            }
+            let hints = match pick {
+                Pick::File => "↑↓ move · Enter open/pick · ← parent · / type a path · r subdirectories · f filter · . hidden · Esc cancel",
+                Pick::Dir => "↑↓ move · Enter open · s pick · ← parent · / type a path · . hidden · Esc cancel",
+            };
+
+            let key = self.key(&panel, hints, None)?;
+            let rows = listing.as_deref().unwrap_or_default();
+            let mut open = None;
+            match key.code {
+                KeyCode::Up => index = index.saturating_sub(1),
+                KeyCode::Down => index = (index + 1).min(rows.len().saturating_sub(1)),
+                KeyCode::Home => index = 0,
+                KeyCode::End => index = rows.len().saturating_sub(1),
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Left | KeyCode::Backspace => open = dir.parent().map(Path::to_path_buf),
+                KeyCode::Char('~') => open = std::env::var_os("HOME").map(PathBuf::from),
+                KeyCode::Char('.') => {
+                    view.show_hidden = !view.show_hidden;
+                    open = Some(dir.clone());
+                }
+                KeyCode::Char('r') if pick == Pick::File => {
+                    view.recursive = !view.recursive;
+                    open = Some(dir.clone());
+                }
+                KeyCode::Char('f') if pick == Pick::File => {
+                    if let Some(filter) = self.input(FILTER_PROMPT)? {
+                        view.extensions = parse_extensions(&filter);
+                        open = Some(dir.clone());
+                    }
+                }
+                KeyCode::Char('/') => {
+                    let Some(path) = self.input_path("Go to:", &dir)? else {
+                        continue;
+                    };
+                    if path.is_dir() {
+                        open = Some(path);
+                    } else if pick == Pick::File && path.is_file() && include(&path) {
+                        say!(self, "{} {}", prompt, paint(Tone::Value, path.display()));
+                        return Ok(Some(path));
+                    } else if path.exists() {
+                        say!(self, "{}", paint(Tone::Error, format_args!("Can't pick {} here.", path.display())));
+                    } else {
+                        say!(self, "{}", paint(Tone::Error, format_args!("No such file or directory: {}", path.display())));
+                    }
+                }
+                KeyCode::Enter | KeyCode::Right => match rows.get(index) {
+                    Some(row) if row.name == "." && key.code == KeyCode::Enter => {
+                        say!(self, "{} {}", prompt, paint(Tone::Value, format_args!("{}/", row.path.display())));
+                        return Ok(Some(row.path.clone()));
+                    }
+                    Some(row) if row.is_dir => open = Some(row.path.clone()),
+                    Some(row) if pick == Pick::File && key.code == KeyCode::Enter => {
+                        say!(self, "{} {}", prompt, paint(Tone::Value, row.path.display()));
+                        return Ok(Some(row.path.clone()));
+                    }
+                    _ => {}
+                },
+                KeyCode::Char('s') if pick == Pick::Dir => {
+                    if let Some(row) = rows.get(index).filter(|r| r.is_dir && r.name != "..") {
+                        say!(self, "{} {}", prompt, paint(Tone::Value, format_args!("{}/", row.path.display())));
+                        return Ok(Some(row.path.clone()));
+                    }
+                }
+                _ => {}
+            }
+
+            if let Some(target) = open {
+                let previous = dir.clone();
+                dir = fs::canonicalize(&target).unwrap_or(target);
+                listing = list_dir(&dir, &view, &include);
+                // Going up lands on the directory just left
+                index = listing.as_deref().unwrap_or_default().iter().position(|r| r.path == previous && r.name != ".." && r.name != ".").unwrap_or_else(|| first_entry(&listing));
+            }
+        }
+    }
+
+    /// Lets the user change each field. Returns false if they backed out with Esc.
+    pub fn form(&mut self, heading: &str, fields: &mut [Field]) -> Result<bool, Error> {
+        if self.plain {
+            return self.form_line(heading, fields);
+        }
+        let mut index = 0;
+        // The first digit typed into a number replaces it, the next ones are added to it
+        let mut typing = false;
+        let label_width = fields.iter().map(|f| f.label.len()).max().unwrap_or(0) + 2;
+        loop {
+            let mut panel = vec![heading.to_string()];
+            for (i, field) in fields.iter().enumerate() {
+                let line = format!("{:<label_width$}‹ {:>5} ›  ", field.label, field.value.show());
+                panel.push(if i == index {
+                    paint(Tone::Selected, format_args!("▸ {}{}", line, field.help))
+                } else {
+                    format!("  {}{}", line, paint(Tone::Muted, field.help))
+                });
+            }
+
+            let key = self.key(&panel, "↑↓ field · ←→ change · type a number · Enter done · Esc cancel", None)?;
+            let value = &mut fields[index].value;
+            let was_typing = std::mem::take(&mut typing);
+            match key.code {
+                KeyCode::Up => index = index.saturating_sub(1),
+                KeyCode::Down | KeyCode::Tab => index = (index + 1).min(fields.len() - 1),
+                KeyCode::Left | KeyCode::Char('-') => value.step(false),
+                KeyCode::Right | KeyCode::Char('+') | KeyCode::Char(' ') => value.step(true),
+                KeyCode::Char(c @ '0'..='9') => {
+                    if let Value::Number { value, max, .. } = value {
+                        let digit = c.to_digit(10).unwrap_or(0) as usize;
+                        let before = if was_typing { *value } else { 0 };
+                        *value = before.saturating_mul(10).saturating_add(digit).min(*max);
+                        typing = true;
+                    }
+                }
+                KeyCode::Backspace => {
+                    if let Value::Number { value, .. } = value {
+                        *value /= 10;
+                        typing = true;
+                    }
+                }
+                KeyCode::Enter => {
+                    let too_small = fields.iter().find_map(|f| match f.value {
+                        Value::Number { value, min, .. } if value < min => Some((f.label, min)),
+                        _ => None,
+                    });
+                    if let Some((label, min)) = too_small {
+                        say!(self, "{}", paint(Tone::Error, format_args!("{} must be at least {}.", label, min)));
+                        continue;
+                    }
+                    let summary = fields.iter().map(|f| format!("{} {}", f.label.to_lowercase(), f.value.show())).collect::<Vec<_>>();
+                    say!(self, "{} {}", heading, paint(Tone::Value, summary.join(", ")));
+                    return Ok(true);
+                }
+                KeyCode::Esc => return Ok(false),
+                _ => {}
+            }
+        }
+    }
+
+    fn history_visible(&self) -> bool {
+        !self.history.is_empty() && terminal::size().is_ok_and(|(w, _)| w as usize >= MIN_WIDTH_FOR_HISTORY)
+    }
+
+    // Draws the screen and waits for a key, keeping the clipboard countdown in the footer up to date.
+    // Page Up/Down scroll the screen, Ctrl-C gives up on the whole session.
+    fn key(&mut self, panel: &[String], hints: &str, in_history: Option<usize>) -> Result<KeyEvent, Error> {
+        if self.interrupted {
+            return Err(interrupted());
+        }
+        self.draw(panel, hints, in_history)?;
+        let mut shown = clipboard::countdown();
+        loop {
+            if !event::poll(TICK)? {
+                let remaining = clipboard::countdown();
+                if remaining != shown {
+                    shown = remaining;
+                    self.draw(panel, hints, in_history)?;
+                }
+                continue;
+            }
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.interrupted = true;
+                        return Err(interrupted());
+                    }
+                    KeyCode::PageUp => {
+                        self.scroll += self.body_height() / 2;
+                        self.draw(panel, hints, in_history)?;
+                    }
+                    KeyCode::PageDown => {
+                        self.scroll = self.scroll.saturating_sub(self.body_height() / 2);
+                        self.draw(panel, hints, in_history)?;
+                    }
+                    _ => return Ok(key),
+                },
+                Event::Resize(..) => self.draw(panel, hints, in_history)?,
+                _ => {}
+            }
+        }
+    }
+
+    fn draw(&mut self, panel: &[String], hints: &str, in_history: Option<usize>) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let (width, height) = (width as usize, height as usize);
+        let history_width = if width >= MIN_WIDTH_FOR_HISTORY { HISTORY_WIDTH } else { 0 };
+        let main_width = width - history_width - usize::from(history_width > 0);
+        let body = height.saturating_sub(4);
+
+        // The transcript and the question below it; when they don't fit, the end is shown
+        let mut content = Vec::new();
+        let blank = String::new();
+        let gap = (!self.lines.is_empty() && !panel.is_empty()).then_some(&blank);
+        for line in self.lines.iter().chain(gap).chain(panel) {
+            content.extend(wrap(line, main_width.saturating_sub(2), true));
+        }
+        self.scroll = self.scroll.min(content.len().saturating_sub(body));
+        let first = content.len().saturating_sub(body + self.scroll);
+
+        let history = self.history_lines(history_width.saturating_sub(1), in_history, body);
+
+        let mut out = io::stdout();
+        queue!(out, BeginSynchronizedUpdate)?;
+        let title = format!(" {} {} {}", paint(Tone::Success, style::text("🔑 crypto-bro")), paint(Tone::Muted, "·"), self.title);
+        let mut rule = "─".repeat(main_width);
+        if history_width > 0 {
+            rule.push('┬');
+            rule.push_str(&"─".repeat(history_width));
+        }
+        self.put(&mut out, 0, &title, width)?;
+        self.put(&mut out, 1, &paint(Tone::Muted, rule), width)?;
+
+        for row in 0..body {
+            let line = content.get(first + row).map(|l| format!(" {}", l)).unwrap_or_default();
+            self.put(&mut out, row + 2, &line, main_width)?;
+            if history_width > 0 {
+                let entry = history.get(row).map(String::as_str).unwrap_or_default();
+                queue!(out, MoveTo(main_width as u16, (row + 2) as u16), Print(format!("{}{}", paint(Tone::Muted, "│"), entry)))?;
+            }
+        }
+
+        let mut rule = "─".repeat(main_width);
+        if history_width > 0 {
+            rule.push('┴');
+            rule.push_str(&"─".repeat(history_width));
+        }
+        let scrolled = if self.scroll > 0 { format!(" {}", paint(Tone::Warning, format_args!("(scrolled back {} lines)", self.scroll))) } else { String::new() };
+        self.put(&mut out, height.saturating_sub(2), &paint(Tone::Muted, rule), width)?;
+        self.put(&mut out, height.saturating_sub(1), &format!(" {}{}", paint(Tone::Muted, hints), scrolled), width)?;
+
+        let status = match clipboard::countdown() {
+            Some(secs) => format!("{} clears in {}s ", style::text("📋"), secs),
+            None if self.clear_pending => format!("{} cleared ", style::text("📋")),
+            None => String::new(),
+        };
+        // Measured as printed, emoji may be written as words
+        if !status.is_empty() && width > columns(&status) {
+            let column = (width - columns(&status)) as u16;
+            queue!(out, MoveTo(column, height.saturating_sub(1) as u16), Print(paint(Tone::Warning, status)))?;
+        }
+        queue!(out, EndSynchronizedUpdate)?;
+        out.flush()
+    }
+
+    // Clears a row and writes a line to it, cut to `width` columns
+    fn put(&self, out: &mut io::Stdout, row: usize, line: &str, width: usize) -> io::Result<()> {
+        let line = cut(line, width);
+        queue!(out, MoveTo(0, row as u16), Clear(ClearType::CurrentLine), Print(line))
+    }
+
+    // The history pane, newest first, scrolled so the selected entry is visible
+    fn history_lines(&self, width: usize, selected: Option<usize>, rows: usize) -> Vec<String> {
+        if width == 0 {
+            return Vec::new();
+        }
+        let mut lines = vec![format!(" {}", paint(Tone::Success, "History"))];
+        if self.history.is_empty() {
+            lines.push(format!(" {}", paint(Tone::Muted, "Nothing yet")));
+        }
+        let mut selected_line = 0;
+        for (i, entry) in self.history.iter().enumerate() {
+            let heading = format!(" {} {}", entry.time, entry.label);
+            let value = entry.value.as_deref().map(|v| format!("   {}", v));
+            if selected == Some(i) {
+                selected_line = lines.len();
+                lines.push(paint(Tone::Selected, fit(&heading, width)));
+                lines.extend(value.map(|v| paint(Tone::Selected, fit(&v, width))));
+            } else {
+                lines.push(fit(&heading, width));
+                lines.extend(value.map(|v| paint(Tone::Value, fit(&v, width))));
+            }
+        }
+        let first = (selected_line + 3).saturating_sub(rows);
+        lines.drain(1..first.max(1).min(lines.len()));
+        lines
+    }
+}
+
+// Plain mode: each question is printed and answered with a line of input
+impl Ui {
+    fn ask_line(&mut self, prompt: &str) -> Result<String, Error> {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+        let answer = prompt::read_line()?;
+        // Piped answers aren't echoed, so a log of the session shows them after their questions
+        if !io::stdin().is_terminal() {
+            println!("{}", answer);
+        }
+        Ok(answer)
+    }
+
+    // Esc can't be pressed here, typing `esc` stands in for it
+    fn choose_line(&mut self, prompt: &str, items: &[Item], selected: char) -> Result<Option<char>, Error> {
+        if !prompt.is_empty() {
+            println!("{}", prompt);
+        }
+        for item in items {
+            match item.detail.as_str() {
+                "" => println!("  [{}] {}", item.key, item.label),
+                detail => println!("  [{}] {} ({})", item.key, item.label, detail),
+            }
+        }
+        loop {
+            let answer = self.ask_line(&format!("Type an option and press Enter ({} if empty, esc to go back): ", selected))?;
+            let answer = answer.trim();
+            if answer.eq_ignore_ascii_case("esc") {
+                return Ok(None);
+            }
+            let mut chars = answer.chars();
+            let key = match (chars.next(), chars.next()) {
+                (None, _) => selected,
+                (Some(c), None) => c,
+                _ => '\0',
+            };
+            match items.iter().find(|i| i.key == key) {
+                Some(item) => return Ok(Some(item.key)),
+                None => println!("{}", paint(Tone::Error, "Invalid option.")),
+            }
+        }
+    }
+
+    fn edit_line(&mut self, prompt: &str, hidden: bool) -> Result<String, Error> {
+        if !hidden {
+            return self.ask_line(&format!("{} ", prompt));
+        }
+        let answer = prompt::read_hidden(&format!("{} ", prompt));
+        // The prompt went to stderr, and only a terminal ends its line
+        if !io::stdin().is_terminal() {
+            eprintln!();
+        }
+        answer.map_err(|e| {
+            if e.kind() == io::ErrorKind::Interrupted {
+                self.interrupted = true;
+                interrupted()
+            } else {
+                e.into()
+            }
+        })
+    }
+
+    // Lists the directory with numbers, and takes a number or a path
+    fn browse_line(&mut self, prompt: &str, mut dir: PathBuf, mut view: View, include: impl Fn(&Path) -> bool) -> Result<Option<PathBuf>, Error> {
+        loop {
+            println!("{}\n{}", prompt, heading(&dir, &view));
+            let listing = list_dir(&dir, &view, &include);
+            match &listing {
+                Ok(rows) => {
+                    for (i, row) in rows.iter().enumerate().take(PLAIN_LIST_LIMIT) {
+                        let label = if row.name == "." { "./ (this directory)".to_string() } else { row.label() };
+                        println!("  {:>3}. {}", i + 1, label);
+                    }
+                    if rows.len() > PLAIN_LIST_LIMIT {
+                        println!("  {}", paint(Tone::Muted, format_args!("… and {} more, type a path to pick one of them", rows.len() - PLAIN_LIST_LIMIT)));
+                    }
+                }
+                Err(e) => println!("  {}", paint(Tone::Error, e)),
+            }
+            let question = match view.pick {
+                Pick::File => "Type a number or a path, r to list subdirectories, f to filter, or nothing to cancel: ",
+                Pick::Dir => "Type a number or a path, or nothing to cancel: ",
+            };
+            let answer = self.ask_line(question)?;
+            let rows = listing.as_deref().unwrap_or_default();
+            match answer.trim() {
+                "" => return Ok(None),
+                "r" if view.pick == Pick::File => view.recursive = !view.recursive,
+                "f" if view.pick == Pick::File => view.extensions = parse_extensions(&self.ask_line(&format!("{} ", FILTER_PROMPT))?),
+                answer => {
+                    let number = answer.parse::<usize>().ok().filter(|n| *n >= 1);
+                    let (path, numbered) = match number.and_then(|n| rows.get(n - 1)) {
+                        Some(row) if row.name == "." => return Ok(Some(row.path.clone())),
+                        Some(row) => (row.path.clone(), true),
+                        None if number.is_some() => {
+                            println!("{}", paint(Tone::Error, format_args!("There is no entry {}.", answer)));
+                            continue;
+                        }
+                        None => (dir.join(expand_home(answer)), false),
+                    };
+                    // Typing a directory's path picks it, its number opens it
+                    if path.is_dir() && (view.pick == Pick::File || numbered) {
+                        dir = fs::canonicalize(&path).unwrap_or(path);
+                    } else if path.exists() && (view.pick == Pick::Dir) == path.is_dir() && include(&path) {
+                        return Ok(Some(path));
+                    } else if path.exists() {
+                        println!("{}", paint(Tone::Error, format_args!("Can't pick {} here.", path.display())));
+                    } else {
+                        println!("{}", paint(Tone::Error, format_args!("No such file or directory: {}", path.display())));
+                    }
+                }
+            }
+        }
+    }
+
+    // Asks for each field in turn, keeping its value when nothing is typed
+    fn form_line(&mut self, heading: &str, fields: &mut [Field]) -> Result<bool, Error> {
+        println!("{}", heading);
+        for field in fields.iter_mut() {
+            loop {
+                let help = if field.help.is_empty() { String::new() } else { format!(" ({})", field.help) };
+                let answer = self.ask_line(&format!("{}{} [{}]: ", field.label, help, field.value.show()))?;
+                if answer.trim().is_empty() {
+                    break;
+                }
+                match field.value.set(answer.trim()) {
+                    Ok(()) => break,
+                    Err(e) => println!("{}", paint(Tone::Error, e)),
+                }
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Drop for Ui {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+fn interrupted() -> Error {
+    Error::Cancelled("Interrupted.".into())
+}
+
+// For callbacks that report io::Error. After Ctrl-C, the next question ends the session anyway.
+fn into_io(e: Error) -> io::Error {
+    match e {
+        Error::Io(e) => e,
+        other => io::Error::new(io::ErrorKind::Interrupted, other.to_string()),
+    }
+}
+
+// The entries of a directory for the browser: its parent, itself when picking directories,
+// then directories, then files. Recursive listings have the files below it and no directories.
+fn list_dir(dir: &Path, view: &View, include: &impl Fn(&Path) -> bool) -> io::Result<Vec<Row>> {
+    let mut rows = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        // Subdirectories that can't be read are skipped
+        let entries = match fs::read_dir(&current) {
+            Ok(entries) => entries,
+            Err(e) if current == dir => return Err(e),
+            Err(_) => continue,
+        };
+        for entry in entries.filter_map(Result::ok) {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') && !view.show_hidden {
+                continue;
+            }
+            let is_dir = path.is_dir();
+            if is_dir && view.recursive {
+                // Links to directories are left out, they could lead back up
+                if entry.file_type().is_ok_and(|t| t.is_dir()) && rows.len() < WALK_LIMIT {
+                    pending.push(path);
+                }
+                continue;
+            }
+            let picked_kind = (view.pick == Pick::Dir) == is_dir;
+            if picked_kind && !include(&path) {
+                continue;
+            }
+            if !is_dir && !view.extensions.is_empty() && !has_extension(&name, &view.extensions) {
+                continue;
+            }
+            let size = (!is_dir).then(|| fs::metadata(&path).map(|m| m.len()).ok()).flatten();
+            let name = path.strip_prefix(dir).map_or(name, |relative| relative.to_string_lossy().to_string());
+            rows.push(Row { path, name, is_dir, size });
+        }
+    }
+    rows.sort_by_key(|r| (!r.is_dir, r.name.to_lowercase()));
+    if view.pick == Pick::Dir && include(dir) {
+        rows.insert(0, Row { path: dir.to_path_buf(), name: ".".into(), is_dir: true, size: None });
+    }
+    if let Some(parent) = dir.parent() {
+        rows.insert(0, Row { path: parent.to_path_buf(), name: "..".into(), is_dir: true, size: None });
+    }
+    Ok(rows)
+}
+
+fn has_extension(name: &str, extensions: &[String]) -> bool {
+    let name = name.to_lowercase();
+    extensions.iter().any(|e| name.strip_suffix(e.as_str()).is_some_and(|stem| stem.len() > 1 && stem.ends_with('.')))
+}
+
+// Where a browser opens: the start, or its closest existing parent if it doesn't exist yet
+fn start_dir(start: &Path) -> io::Result<PathBuf> {
+    match start.ancestors().find_map(|a| fs::canonicalize(a).ok()) {
+        Some(dir) => Ok(dir),
+        None => std::env::current_dir(),
+    }
+}
+
+// The browser's directory and how its listing is narrowed down
+fn heading(dir: &Path, view: &View) -> String {
+    let mut heading = paint(Tone::Dir, dir.display());
+    if view.recursive {
+        heading.push_str(&format!("  {}", paint(Tone::Muted, "[all subdirectories]")));
+    }
+    if !view.extensions.is_empty() {
+        heading.push_str(&format!("  {}", paint(Tone::Muted, format_args!("[only .{}]", view.extensions.join(", .")))));
+    }
+    heading
+}
+
+// "txt, .env *.md" -> txt, env, md
+fn parse_extensions(text: &str) -> Vec<String> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .map(|e| e.trim_start_matches('*').trim_start_matches('.').to_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+// Past the parent directory and the directory itself, if there is anything else
+fn first_entry(listing: &io::Result<Vec<Row>>) -> usize {
+    listing.as_ref().map_or(0, |rows| rows.iter().position(|r| r.name != ".." && r.name != ".").unwrap_or(0))
+}
+
+/// Expands a leading `~` to the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => PathBuf::from(home).join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+// Completes the last part of a typed path as far as the matching names agree.
+// Returns the new text and, when several names still match, those names.
+fn complete_path(text: &str, base: &Path) -> (String, Vec<String>) {
+    if text == "~" {
+        return ("~/".into(), Vec::new());
+    }
+    let (typed_dir, prefix) = text.rfind('/').map_or(("", text), |i| (&text[..=i], &text[i + 1..]));
+    let dir = base.join(expand_home(typed_dir));
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return (text.to_string(), Vec::new());
+    };
+    let mut matches = entries
+        .filter_map(Result::ok)
+        .map(|e| (e.file_name().to_string_lossy().to_string(), e.path().is_dir()))
+        .filter(|(name, _)| name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.')))
+        .collect::<Vec<_>>();
+    matches.sort();
+    match matches.as_slice() {
+        [] => (text.to_string(), Vec::new()),
+        [(name, is_dir)] => (format!("{}{}{}", typed_dir, name, if *is_dir { "/" } else { "" }), Vec::new()),
+        [(first, _), rest @ ..] => {
+            let mut common = first.len();
+            for (name, _) in rest {
+                common = first.char_indices().zip(name.chars()).take_while(|((_, a), b)| a == b).map(|((i, a), _)| i + a.len_utf8()).last().unwrap_or(0).min(common);
+            }
+            let names = matches.iter().map(|(name, is_dir)| if *is_dir { format!("{}/", name) } else { name.clone() }).collect();
+            (format!("{}{}", typed_dir, &first[..common]), names)
+        }
+    }
+}
+
+fn size_label(bytes: u64) -> String {
+    match bytes {
+        b if b < 1024 => format!("{} B", b),
+        b if b < 1024 * 1024 => format!("{:.1} KiB", b as f64 / 1024.0),
+        b => format!("{:.1} MiB", b as f64 / 1024.0 / 1024.0),
+    }
+}
+
+// The first `width` columns of a line
+fn cut(line: &str, width: usize) -> String {
+    wrap(line, width, false).into_iter().next().unwrap_or_default()
+}
+
+// Cuts a line to `width` columns, marking that it was cut
+fn fit(line: &str, width: usize) -> String {
+    if columns(line) <= width {
+        return line.to_string();
+    }
+    format!("{}…", cut(line, width.saturating_sub(1)))
+}
+
+// Splits a line into pieces at most `width` columns wide, carrying its colour codes over to each piece.
+// With `at_spaces`, lines are broken between words where they can be.
+fn wrap(line: &str, width: usize, at_spaces: bool) -> Vec<String> {
+    let width = width.max(1);
+    let mut pieces = Vec::new();
+    let mut piece = String::new();
+    let mut used = 0;
+    let mut colour = String::new();
+    // Where the piece could be broken: byte offset, columns before it and the colour there
+    let mut space: Option<(usize, usize, String)> = None;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            let mut code = String::from(c);
+            while let Some(&next) = chars.peek() {
+                code.push(next);
+                chars.next();
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+            if code == style::RESET {
+                colour.clear();
+            } else if code.ends_with('m') {
+                colour.push_str(&code);
+            }
+            piece.push_str(&code);
+            continue;
+        }
+        let w = char_width(c);
+        if used + w > width && used > 0 {
+            let rest = match space.take() {
+                Some((at, before, at_colour)) if c != ' ' => {
+                    let rest = format!("{}{}", at_colour, &piece[at + 1..]);
+                    piece.truncate(at);
+                    used -= before + 1;
+                    if !at_colour.is_empty() {
+                        piece.push_str(style::RESET);
+                    }
+                    rest
+                }
+                _ => {
+                    if !colour.is_empty() {
+                        piece.push_str(style::RESET);
+                    }
+                    used = 0;
+                    colour.clone()
+                }
+            };
+            pieces.push(std::mem::replace(&mut piece, rest));
+            if c == ' ' && used == 0 {
+                continue;
+            }
+        }
+        if at_spaces && c == ' ' && used > 0 {
+            space = Some((piece.len(), used, colour.clone()));
+        }
+        piece.push(c);
+        used += w;
+    }
+    pieces.push(piece);
+    pieces
+}
+
+// Columns a line takes up on screen, without its colour codes
+fn columns(line: &str) -> usize {
+    style::strip_color(line).chars().map(char_width).sum()
+}
+
+// Terminal columns of a character: 0 for combining marks, 2 for emoji and East Asian wide characters.
+// The emoji variation selector widens the symbol before it, e.g. ⚠️.
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F => 0,
+        0xFE0F => 1,
+        0x1100..=0x115F
+        | 0x2705
+        | 0x274C
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1FAFF => 2,
+        _ => 1,
+    }
+}